@@ -1,15 +1,63 @@
-use ansi_term::{Colour::Fixed, Style};
+use ansi_term::{Colour, Style};
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
-use owo_colors::OwoColorize;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 use zellij_tile::prelude::*;
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 enum View {
     Session,
     Tab,
     Pane,
+    Command,
+    Entry,
+    Project,
+    Layout,
+    Bookmark,
+}
+
+impl View {
+    const ALL: [View; 8] = [
+        View::Tab,
+        View::Pane,
+        View::Session,
+        View::Bookmark,
+        View::Command,
+        View::Layout,
+        View::Entry,
+        View::Project,
+    ];
+
+    /// stable identifier, used in the saved history
+    fn id(self) -> &'static str {
+        match self {
+            View::Tab => "tab",
+            View::Pane => "pane",
+            View::Session => "session",
+            View::Command => "command",
+            View::Entry => "entry",
+            View::Project => "project",
+            View::Layout => "layout",
+            View::Bookmark => "bookmark",
+        }
+    }
+
+    /// ribbon labels, from the longest to the shortest
+    fn labels(self) -> [&'static str; 3] {
+        match self {
+            View::Tab => ["Tabs Selector", "Tabs", "T"],
+            View::Pane => ["Panes Selector", "Panes", "P"],
+            View::Session => ["Sessions Selector", "Sessions", "S"],
+            View::Command => ["Command Palette", "Commands", "C"],
+            View::Entry => ["Custom Entries", "Entries", "E"],
+            View::Project => ["Projects", "Projects", "J"],
+            View::Layout => ["Layouts", "Layouts", "L"],
+            View::Bookmark => ["Bookmarks", "Bookmarks", "B"],
+        }
+    }
 }
 
 struct State {
@@ -24,11 +72,93 @@ struct State {
     input_cusror_index: usize,
     tab_match: Option<usize>,
     session_match: Option<String>,
+    command_match: Option<usize>,
     pane_match: Option<u32>,
     pane_title_match: String,
     // sessions: Vec<SessionInfo>,
     sessions: Vec<String>,
     fz_matcher: SkimMatcherV2,
+    theme: Theme,
+
+    // (tab position, pane id) focused at the last update,
+    // followed by the sidebar only when it changes
+    focus: Option<(usize, Option<u32>)>,
+
+    // last rendered positions, for mouse events
+    ribbon_hitboxes: Vec<(std::ops::Range<usize>, View)>,
+    list_y: usize,
+    list_entries: Vec<usize>,
+
+    hint_mode: bool,
+    hint_input: String,
+
+    // panes (id, title) being moved to another tab
+    moving_panes: Vec<(u32, String)>,
+
+    // marked entries for batch operations
+    marked_tabs: BTreeSet<usize>,
+    marked_panes: BTreeSet<u32>,
+    marked_sessions: BTreeSet<String>,
+    current_session: Option<String>,
+
+    overlay: Option<Overlay>,
+    // (renamed target, previous name), cleared after a timeout
+    undo_rename: Option<(RenameTarget, String)>,
+    undo_timers: usize,
+
+    // working directory the plugin was launched from, the plugin
+    // API does not expose the working directory of the panes
+    cwd: PathBuf,
+    // custom entries declared in the configuration
+    entries: Vec<Entry>,
+    // "name: reason" of the entries that could not be parsed
+    entry_errors: Vec<String>,
+    // home directory of the user, to expand `~/` in entry paths
+    home: Option<PathBuf>,
+
+    // project directories found under the `project_roots`
+    projects: Vec<String>,
+    project_match: Option<usize>,
+    scanning_projects: bool,
+    // first error line of the last scan, when it failed
+    project_error: Option<String>,
+
+    // layouts available in the current session
+    layouts: Vec<LayoutInfo>,
+    layout_match: Option<usize>,
+    // (tab name, tab count) of a tab opened from a layout,
+    // renamed once it shows up in a TabUpdate
+    pending_layout_tab: Option<(String, usize)>,
+
+    // tabs and panes bookmarked with a letter
+    bookmarks: BTreeMap<char, Bookmark>,
+    bookmark_match: Option<usize>,
+    bookmarks_loaded: bool,
+    // pane bookmarks read from disk, whose ids may belong to
+    // other panes after a resurrection: attached by title
+    pending_bookmarks: Vec<(char, Bookmark)>,
+
+    // name patterns of the tabs, panes and sessions listed first
+    pins: Vec<String>,
+
+    // aliases and #tags searched along with the names
+    annotations: BTreeMap<Annotated, String>,
+    // pane annotations read from disk, attached to the panes
+    // by id and title (or tab and title) once they are listed
+    pending_annotations: Vec<(Bookmark, String)>,
+
+    // confirmed queries of each view, the most recent last
+    history: HashMap<View, Vec<String>>,
+    // position in the history while recalling queries
+    history_pos: Option<usize>,
+
+    // query and selection of the views left
+    view_states: HashMap<View, ViewState>,
+
+    // name of the selected entry, when it disappeared in an update
+    selection_lost: Option<String>,
+
+    permissions: Permissions,
 }
 
 impl Default for State {
@@ -45,70 +175,143 @@ impl Default for State {
             input_cusror_index: 0,
             tab_match: None,
             session_match: None,
+            command_match: None,
             pane_match: None,
             pane_title_match: String::default(),
             sessions: Vec::default(),
             fz_matcher: SkimMatcherV2::default(),
+            theme: Theme::default(),
+            focus: None,
+            ribbon_hitboxes: Vec::default(),
+            list_y: 0,
+            list_entries: Vec::default(),
+            hint_mode: false,
+            hint_input: String::default(),
+            moving_panes: Vec::default(),
+            marked_tabs: BTreeSet::default(),
+            marked_panes: BTreeSet::default(),
+            marked_sessions: BTreeSet::default(),
+            current_session: None,
+            overlay: None,
+            undo_rename: None,
+            undo_timers: 0,
+            cwd: PathBuf::default(),
+            entries: Vec::default(),
+            entry_errors: Vec::default(),
+            home: None,
+            projects: Vec::default(),
+            project_match: None,
+            scanning_projects: false,
+            project_error: None,
+            layouts: Vec::default(),
+            layout_match: None,
+            pending_layout_tab: None,
+            bookmarks: BTreeMap::default(),
+            bookmark_match: None,
+            bookmarks_loaded: false,
+            pending_bookmarks: Vec::default(),
+            pins: Vec::default(),
+            annotations: BTreeMap::default(),
+            pending_annotations: Vec::default(),
+            history: HashMap::default(),
+            history_pos: None,
+            view_states: HashMap::default(),
+            selection_lost: None,
+            permissions: Permissions::RequestingRead,
         }
     }
 }
 
 impl State {
     fn handle_key_event(&mut self, key: KeyWithModifier) -> bool {
+        let closing = key.bare_key == BareKey::Esc
+            || (key.bare_key == BareKey::Char('c') && key.has_modifiers(&[KeyModifier::Ctrl]));
+        match self.permissions {
+            Permissions::Denied => {
+                if closing {
+                    self.close();
+                } else if key.bare_key == BareKey::Enter {
+                    self.request_read_permission();
+                }
+                return true;
+            }
+            Permissions::ReadOnly
+                if key.bare_key == BareKey::Char('r') && key.has_modifiers(&[KeyModifier::Alt]) =>
+            {
+                self.permissions = Permissions::RequestingActions;
+                self.request_action_permissions();
+                return true;
+            }
+            // browse only, the actions need to change the session
+            // (confirm also checks it, plain keys may confirm)
+            Permissions::RequestingRead
+            | Permissions::RequestingActions
+            | Permissions::RequestingReadOnly
+            | Permissions::ReadOnly
+                if !closing && !is_browsing_key(&key) =>
+            {
+                return false;
+            }
+            _ => (),
+        }
+
+        if self.overlay.is_some() {
+            return self.handle_overlay_key_event(key);
+        }
+        if self.hint_mode {
+            return self.handle_hint_key_event(key);
+        }
+
+        self.selection_lost = None;
         let mut should_render = true;
         match key.bare_key {
-            BareKey::Enter => match self.current_view {
-                View::Tab => {
-                    if let Some(p) = self.tab_match {
-                        close_focus();
-                        switch_tab_to(p as u32 + 1);
-                    }
-                }
-                View::Pane => {
-                    if let Some(pane_id) = self.pane_match {
-                        close_focus();
-                        focus_terminal_pane(pane_id, true);
-                    }
-                }
-                View::Session => {
-                    if let Some(sess) = &self.session_match {
-                        close_focus();
-                        switch_session(Some(sess));
-                    }
-                }
-            },
+            BareKey::Enter
+                if key.has_modifiers(&[KeyModifier::Alt]) && !self.moving_panes.is_empty() =>
+            {
+                self.move_pane_to_new_tab();
+            }
+            BareKey::Enter
+                if key.has_modifiers(&[KeyModifier::Alt]) && self.shell_command().is_some() =>
+            {
+                self.run_shell_command(Placement::NewTab);
+            }
+            BareKey::Char('w')
+                if key.has_modifiers(&[KeyModifier::Alt]) && self.shell_command().is_some() =>
+            {
+                self.run_shell_command(Placement::Floating);
+            }
+            BareKey::Enter
+                if key.has_modifiers(&[KeyModifier::Alt]) && self.current_view == View::Project =>
+            {
+                self.confirm_project(true);
+            }
+            BareKey::Enter
+                if key.has_modifiers(&[KeyModifier::Alt]) && self.current_view == View::Layout =>
+            {
+                self.start_layout_session();
+            }
+            BareKey::Enter if self.shell_command().is_some() => {
+                self.run_shell_command(Placement::Tiled);
+            }
+            BareKey::Enter => self.confirm(),
             BareKey::Backspace => {
+                self.history_pos = None;
                 if self.remove_input_at_index() {
-                    match self.current_view {
-                        View::Tab => {
-                            self.fuzzy_find_tab();
-                        }
-
-                        View::Pane => {
-                            self.fuzzy_find_pane();
-                        }
-                        View::Session => {
-                            self.fuzzy_find_session();
-                        }
-                    }
+                    self.fuzzy_find();
                 }
                 should_render = true;
             }
+            BareKey::Down if self.history_pos.is_some() => {
+                self.recall_newer_query();
+                should_render = true;
+            }
+            BareKey::Up if self.can_recall_query() => {
+                self.recall_older_query();
+                should_render = true;
+            }
 
             BareKey::Down => {
-                match self.current_view {
-                    View::Tab => {
-                        self.move_down_tab();
-                    }
-
-                    View::Pane => {
-                        self.move_down_pane();
-                    }
-                    View::Session => {
-                        self.move_down_session();
-                    }
-                }
-
+                self.move_down();
                 should_render = true;
             }
             BareKey::PageUp => {
@@ -119,18 +322,7 @@ impl State {
                 should_render = true;
             }
             BareKey::Up => {
-                match self.current_view {
-                    View::Tab => {
-                        self.move_up_tab();
-                    }
-
-                    View::Pane => {
-                        self.move_up_pane();
-                    }
-                    View::Session => {
-                        self.move_up_session();
-                    }
-                }
+                self.move_up();
                 should_render = true;
             }
             BareKey::Left => {
@@ -146,6 +338,10 @@ impl State {
                 should_render = true;
             }
 
+            BareKey::Esc if !self.moving_panes.is_empty() => {
+                self.cancel_move_pane();
+                should_render = true;
+            }
             BareKey::Esc => {
                 self.close();
                 should_render = true;
@@ -155,24 +351,87 @@ impl State {
                 should_render = true;
             }
 
+            BareKey::Char(c) if c.is_ascii_digit() && key.has_modifiers(&[KeyModifier::Alt]) => {
+                self.quick_select(c);
+                should_render = true;
+            }
+            BareKey::Char(c)
+                if c.is_ascii_digit()
+                    && key.has_no_modifiers()
+                    && self.config_flag("quick_select")
+                    && self.shell_command().is_none() =>
+            {
+                self.quick_select(c);
+                should_render = true;
+            }
+
+            BareKey::Char(' ')
+                if key.has_modifiers(&[KeyModifier::Ctrl])
+                    || (key.has_no_modifiers() && self.input.is_empty()) =>
+            {
+                self.toggle_mark();
+                self.move_down();
+                should_render = true;
+            }
+            BareKey::Char('d') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                self.close_marked();
+                should_render = true;
+            }
+            BareKey::Char('r') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                self.start_rename();
+                should_render = true;
+            }
+            BareKey::Char('z') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                self.undo_last_rename();
+                should_render = true;
+            }
+
+            BareKey::Char('l') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                self.cycle_swap_layout(true);
+                should_render = true;
+            }
+            BareKey::Char('k') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                self.cycle_swap_layout(false);
+                should_render = true;
+            }
+
+            BareKey::Char('a') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                self.start_annotate();
+                should_render = true;
+            }
+            BareKey::Char('p') if key.has_modifiers(&[KeyModifier::Alt]) => {
+                self.toggle_pin();
+                should_render = true;
+            }
+            BareKey::Char('m') if key.has_modifiers(&[KeyModifier::Alt]) => {
+                if self.selected_bookmark().is_some() {
+                    self.overlay = Some(Overlay::Bookmark);
+                }
+                should_render = true;
+            }
+            BareKey::Char('\'') if key.has_no_modifiers() && self.input.is_empty() => {
+                self.overlay = Some(Overlay::Jump);
+                should_render = true;
+            }
+
+            BareKey::Char('x') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                self.start_move_pane();
+                should_render = true;
+            }
+
+            BareKey::Char('f') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                self.enter_hint_mode();
+                should_render = true;
+            }
+
             BareKey::Tab => {
                 self.change_mode();
                 should_render = true;
             }
             BareKey::Char(c) => {
+                self.history_pos = None;
                 if self.insert_input_at_index(c) {
-                    match self.current_view {
-                        View::Tab => {
-                            self.fuzzy_find_tab();
-                        }
-
-                        View::Pane => {
-                            self.fuzzy_find_pane();
-                        }
-                        View::Session => {
-                            self.fuzzy_find_session();
-                        }
-                    }
+                    self.fuzzy_find();
                 }
                 should_render = true;
             }
@@ -182,774 +441,4713 @@ impl State {
         should_render
     }
 
-    /// close current plugins and its hepler pane
-    /// get the focused tab position
-    fn get_focused_tab(&mut self) {
-        for (i, t) in self.tab_infos.iter().enumerate() {
-            if t.active {
-                self.focus_tab_pos = t.position;
-                if self.tab_match.is_none() {
-                    self.tab_match = Some(i);
+    /// in hint mode, typed chars select the pane with
+    /// the matching label
+    fn handle_hint_key_event(&mut self, key: KeyWithModifier) -> bool {
+        match key.bare_key {
+            BareKey::Char('c') if key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                self.exit_hint_mode();
+                self.close();
+            }
+            BareKey::Esc => {
+                self.exit_hint_mode();
+            }
+            BareKey::Backspace => {
+                self.hint_input.pop();
+            }
+            BareKey::Char(c) => {
+                self.hint_input.push(c);
 
-                    if let View::Tab = self.current_view {
-                        self.result_index = i;
-                    }
+                let labels = self.hint_labels();
+                if let Some((_, i, pane)) = labels
+                    .iter()
+                    .find(|(label, _, _)| *label == self.hint_input)
+                {
+                    self.pane_match = Some(pane.id);
+                    self.pane_title_match = pane.title.to_owned();
+                    self.result_index = *i;
+                    self.exit_hint_mode();
+                    self.confirm();
+                } else if !labels
+                    .iter()
+                    .any(|(label, _, _)| label.starts_with(&self.hint_input))
+                {
+                    // no label can match anymore
+                    self.hint_input.clear();
                 }
             }
+            _ => (),
         }
+        true
     }
 
-    fn close(&self) {
-        close_plugin_pane(get_plugin_ids().plugin_id);
+    fn enter_hint_mode(&mut self) {
+        if self.current_view != View::Pane {
+            self.set_view(View::Pane);
+        }
+        // a #tag query keeps labelling the tagged panes of all tabs
+        if !self.input.starts_with(TAG_PREFIX) {
+            self.input = String::default();
+            self.input_cusror_index = 0;
+        }
+        self.hint_input.clear();
+        self.hint_mode = true;
     }
 
-    fn change_mode(&mut self) {
-        // reset input
-        self.input = String::default();
-        self.input_cusror_index = 0;
-
-        match self.current_view {
-            View::Tab => {
-                self.current_view = View::Pane;
-            }
+    fn exit_hint_mode(&mut self) {
+        self.hint_input.clear();
+        self.hint_mode = false;
+    }
 
-            View::Pane => {
-                self.current_view = View::Session;
-            }
-            View::Session => {
-                self.current_view = View::Tab;
+    /// keys used to build hint labels
+    fn hint_keys(&self) -> Vec<char> {
+        let mut keys: Vec<char> = vec![];
+        for key in self
+            .userspace_configuration
+            .get("hint_keys")
+            .map(|k| k.chars().filter(|c| !c.is_whitespace()))
+            .into_iter()
+            .flatten()
+        {
+            // duplicate keys would make labels ambiguous
+            if !keys.contains(&key) {
+                keys.push(key);
             }
         }
-
-        if let View::Pane = self.current_view {
-            // pane view
-            self.pane_match = None;
-            self.pane_title_match = String::default();
-            self.result_index = 0;
-
-            self.get_pane_at_index();
-            return;
+        // a single key cannot build distinct labels
+        if keys.len() < 2 {
+            DEFAULT_HINT_KEYS.chars().collect()
+        } else {
+            keys
         }
+    }
 
-        // tab view
-        if let Some(i) = self.tab_match {
-            self.result_index = i;
-        }
+    /// label, index and pane of each terminal pane of the selected tab
+    fn hint_labels(&self) -> Vec<(String, usize, PaneInfo)> {
+        let panes = self.scoped_panes();
+        let rows = self.pane_rows();
+        hint_labels(rows.len(), &self.hint_keys())
+            .into_iter()
+            .zip(rows)
+            .map(|(label, i)| (label, i, panes[i].1.clone()))
+            .collect()
     }
 
-    fn fuzzy_find_tab(&mut self) {
-        let mut best_score = 0;
+    fn handle_mouse_event(&mut self, mouse: Mouse) -> bool {
+        if self.overlay.is_some() {
+            return false;
+        }
 
-        // reset match
-        self.tab_match = None;
-        self.result_index = 0;
-        for (i, t) in self.tab_infos.iter().enumerate() {
-            if let Some(score) = self.fz_matcher.fuzzy_match(t.name.as_str(), &self.input) {
-                if score > best_score {
-                    best_score = score;
-                    self.tab_match = Some(i);
-                    self.result_index = i;
+        match mouse {
+            Mouse::ScrollUp(lines) => {
+                for _ in 0..lines {
+                    self.move_up();
                 }
+                true
             }
-        }
+            Mouse::ScrollDown(lines) => {
+                for _ in 0..lines {
+                    self.move_down();
+                }
+                true
+            }
+            Mouse::LeftClick(line, col) => {
+                if line < 0 {
+                    return false;
+                }
+                let line = line as usize;
 
-        // if no found default to focus tab
-        if self.tab_match.is_none() {
-            self.tab_match = Some(self.focus_tab_pos);
-            self.result_index = self.focus_tab_pos;
-        }
-    }
+                // ribbons
+                if line == 0 {
+                    if let Some((_, view)) = self
+                        .ribbon_hitboxes
+                        .iter()
+                        .find(|(range, _)| range.contains(&col))
+                    {
+                        let view = *view;
+                        if view != self.current_view {
+                            self.switch_view(view);
+                            return true;
+                        }
+                    }
+                    return false;
+                }
 
-    fn seek_tab(&mut self, idx: usize) {
-        self.result_index = idx;
-        for (i, t) in self.tab_infos.iter().enumerate() {
-            if (self.input == String::default()
-                || self
-                    .fz_matcher
-                    .fuzzy_match(t.name.as_str(), &self.input)
-                    .is_some())
-                && i == self.result_index
-            {
-                self.tab_match = Some(i);
-                self.result_index = i;
-                break;
+                // result list
+                if let Some(i) = line
+                    .checked_sub(self.list_y)
+                    .and_then(|row| self.list_entries.get(row))
+                {
+                    let i = *i;
+                    if i == self.result_index {
+                        // click on the selected entry
+                        self.confirm();
+                    } else {
+                        self.select_index(i);
+                    }
+                    return true;
+                }
+                false
             }
+            _ => false,
         }
     }
 
-    fn move_down_tab(&mut self) {
-        let mut first_match = None;
-        let mut seek_result = false;
-        let mut found_next = None;
+    /// confirm the visible row with the given digit index
+    fn quick_select(&mut self, digit: char) {
+        let Some(row) = digit_row(digit) else {
+            return;
+        };
+        if let Some(i) = self.list_entries.get(row) {
+            self.select_index(*i);
+            self.confirm();
+        }
+    }
 
-        for (i, t) in self.tab_infos.iter().enumerate() {
-            if self.input == String::default()
-                || self
-                    .fz_matcher
-                    .fuzzy_match(t.name.as_str(), &self.input)
-                    .is_some()
-            {
-                if first_match.is_none() {
-                    first_match = Some(i);
+    /// confirm the current selection
+    fn confirm(&mut self) {
+        if !self.can_act() {
+            return;
+        }
+        match self.current_view {
+            View::Tab if !self.moving_panes.is_empty() => {
+                self.move_pane_to_tab();
+            }
+            View::Tab => {
+                let position = self
+                    .tab_match
+                    .and_then(|m| self.tab_infos.get(m))
+                    .map(|t| t.position);
+                if let Some(position) = position {
+                    self.dismiss();
+                    switch_tab_to(position as u32 + 1);
                 }
-
-                if i == self.result_index {
-                    seek_result = true;
-                    continue;
+            }
+            View::Pane => {
+                if let Some(pane_id) = self.pane_match {
+                    self.dismiss();
+                    focus_terminal_pane(pane_id, true);
                 }
-
-                if seek_result {
-                    found_next = Some(i);
-                    self.tab_match = Some(i);
-                    self.result_index = i;
-                    break;
+            }
+            View::Session => {
+                if let Some(sess) = self.session_match.clone() {
+                    self.dismiss();
+                    switch_session(Some(&sess));
+                }
+            }
+            View::Command | View::Entry => {
+                if let Some(i) = self.command_match {
+                    self.run_palette_item(i);
+                }
+            }
+            View::Project => self.confirm_project(false),
+            View::Layout => self.open_layout_tab(),
+            View::Bookmark => {
+                let letter = self
+                    .bookmark_match
+                    .and_then(|i| self.bookmarks.keys().nth(i))
+                    .copied();
+                if let Some(letter) = letter {
+                    self.jump_to_bookmark(letter);
                 }
             }
         }
+    }
 
-        if found_next.is_none() {
-            if let Some(i) = first_match {
-                self.tab_match = Some(i);
-                self.result_index = i;
+    /// bookmark of the selected tab or pane
+    fn selected_bookmark(&self) -> Option<Bookmark> {
+        match self.current_view {
+            View::Tab => {
+                let tab = self.tab_match.and_then(|p| self.tab_infos.get(p))?;
+                Some(Bookmark::Tab(tab.name.to_owned()))
+            }
+            View::Pane => {
+                let position = self.selected_pane_tab()?;
+                let tab = self.tab_infos.iter().find(|t| t.position == position)?;
+                self.pane_match.map(|id| Bookmark::Pane {
+                    id,
+                    tab: tab.name.to_owned(),
+                    title: self.pane_title_match.to_owned(),
+                })
             }
+            _ => None,
         }
     }
 
-    fn move_up_tab(&mut self) {
-        let mut prev_match = None;
-        let mut last_match = None;
+    /// bookmark of the focused pane
+    fn focused_bookmark(&self) -> Option<Bookmark> {
+        let tab = self.tab_infos.iter().find(|t| t.active)?;
+        let pane = get_focused_pane(tab.position, &self.pane_manifest)?;
+        Some(Bookmark::Pane {
+            id: pane.id,
+            tab: tab.name.to_owned(),
+            title: pane.title,
+        })
+    }
 
-        for (i, t) in self.tab_infos.iter().enumerate() {
-            if self.input == String::default()
-                || self
-                    .fz_matcher
-                    .fuzzy_match(t.name.as_str(), &self.input)
-                    .is_some()
-            {
-                if i == self.result_index && prev_match.is_some() {
-                    break;
-                }
-                prev_match = Some(i);
-                last_match = Some(i);
+    fn set_bookmark(&mut self, letter: char, bookmark: Bookmark) {
+        self.bookmarks.insert(letter, bookmark);
+        self.save_bookmarks();
+    }
+
+    /// tab position and pane id of a bookmark made in this session:
+    /// panes are found by id, then by title in the bookmarked tab
+    fn resolve_bookmark(&self, bookmark: &Bookmark) -> Option<(usize, Option<u32>)> {
+        self.resolve_pane_bookmark(bookmark, false)
+    }
+
+    /// tab position and pane id of a bookmark read from disk: pane ids
+    /// are reused after a resurrection, so an id alone is not enough
+    /// and panes are found by id and title, then by title in the tab
+    fn resolve_saved_bookmark(&self, bookmark: &Bookmark) -> Option<(usize, Option<u32>)> {
+        self.resolve_pane_bookmark(bookmark, true)
+    }
+
+    fn resolve_pane_bookmark(
+        &self,
+        bookmark: &Bookmark,
+        saved: bool,
+    ) -> Option<(usize, Option<u32>)> {
+        let tab_position = |name: &str| {
+            self.tab_infos
+                .iter()
+                .find(|t| t.name == name)
+                .map(|t| t.position)
+        };
+        match bookmark {
+            Bookmark::Tab(name) => tab_position(name).map(|p| (p, None)),
+            Bookmark::Pane { id, tab, title } => {
+                let panes: Vec<(usize, &PaneInfo)> = self
+                    .pane_manifest
+                    .panes
+                    .iter()
+                    .flat_map(|(p, panes)| panes.iter().map(move |pane| (*p, pane)))
+                    .filter(|(_, pane)| !pane.is_plugin)
+                    .collect();
+                panes
+                    .iter()
+                    .find(|(_, pane)| pane.id == *id && (!saved || pane.title == *title))
+                    .or_else(|| {
+                        let position = tab_position(tab)?;
+                        panes
+                            .iter()
+                            .find(|(p, pane)| *p == position && pane.title == *title)
+                    })
+                    .map(|(p, pane)| (*p, Some(pane.id)))
             }
         }
+    }
 
-        if let Some(i) = prev_match {
-            self.tab_match = Some(i);
-            self.result_index = i;
+    fn bookmark_target(&self, letter: char) -> Option<(usize, Option<u32>)> {
+        self.bookmarks
+            .get(&letter)
+            .and_then(|b| self.resolve_bookmark(b))
+    }
+
+    fn jump_to_bookmark(&mut self, letter: char) {
+        if !self.can_act() {
             return;
         }
+        if let Some(target) = self.bookmark_target(letter) {
+            self.dismiss();
+            focus_target(target);
+        } else if self.bookmarks.contains_key(&letter) {
+            self.selection_lost = Some(format!("bookmark {}", letter));
+        }
+    }
 
-        if let Some(i) = last_match {
-            self.tab_match = Some(i);
-            self.result_index = i;
+    /// bookmarks file of the current session in the plugin data dir,
+    /// when bookmarks are persisted
+    fn bookmarks_path(&self) -> Option<PathBuf> {
+        if !self.config_flag("persist_bookmarks") {
+            return None;
         }
+        let session = self.current_session.as_ref()?;
+        Some(PathBuf::from(DATA_DIR).join(format!("bookmarks-{}", session)))
     }
 
-    fn move_down_session(&mut self) {
-        let mut first_match = None;
-        let mut seek_result = false;
-        let mut found_next = None;
+    fn save_bookmarks(&self) {
+        let Some(path) = self.bookmarks_path() else {
+            return;
+        };
+        // not attached yet, kept for a later session
+        let pending = self
+            .pending_bookmarks
+            .iter()
+            .filter(|(letter, _)| !self.bookmarks.contains_key(letter))
+            .map(|(letter, bookmark)| (letter, bookmark.clone()));
+        let lines: Vec<String> = self
+            .bookmarks
+            .iter()
+            .map(|(letter, bookmark)| (letter, self.live_bookmark(bookmark)))
+            .chain(pending)
+            .map(|(letter, bookmark)| match bookmark {
+                Bookmark::Tab(name) => format!("{}\ttab\t{}", letter, escape_field(&name)),
+                Bookmark::Pane { id, tab, title } => format!(
+                    "{}\tpane\t{}\t{}\t{}",
+                    letter,
+                    id,
+                    escape_field(&tab),
+                    escape_field(&title)
+                ),
+            })
+            .collect();
+        let _ = std::fs::write(path, lines.join("\n"));
+    }
 
-        for (i, session) in self.sessions.iter().enumerate() {
-            if self.input == String::default()
-                || self.fz_matcher.fuzzy_match(session, &self.input).is_some()
-            {
-                if first_match.is_none() {
-                    first_match = Some(i);
-                }
+    /// bookmark with the current tab name and title of its pane,
+    /// used to find it again in a resurrected session
+    fn live_bookmark(&self, bookmark: &Bookmark) -> Bookmark {
+        if let Bookmark::Pane { id, .. } = bookmark {
+            let live = self.find_pane(*id).and_then(|(position, pane)| {
+                let tab = self.tab_infos.iter().find(|t| t.position == position)?;
+                Some(Bookmark::Pane {
+                    id: *id,
+                    tab: tab.name.to_owned(),
+                    title: pane.title.to_owned(),
+                })
+            });
+            if let Some(live) = live {
+                return live;
+            }
+        }
+        bookmark.clone()
+    }
 
-                if i == self.result_index {
-                    seek_result = true;
-                    continue;
+    fn load_bookmarks(&mut self) {
+        let Some(content) = self
+            .bookmarks_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+        else {
+            return;
+        };
+        for line in content.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let bookmark = match fields.as_slice() {
+                [letter, "tab", name] => letter
+                    .chars()
+                    .next()
+                    .map(|l| (l, Bookmark::Tab(unescape_field(name)))),
+                [letter, "pane", id, tab, title] => {
+                    letter.chars().next().zip(id.parse().ok()).map(|(l, id)| {
+                        (
+                            l,
+                            Bookmark::Pane {
+                                id,
+                                tab: unescape_field(tab),
+                                title: unescape_field(title),
+                            },
+                        )
+                    })
                 }
-
-                if seek_result {
-                    if let Some(sess) = self.sessions.get(i) {
-                        found_next = Some(i);
-                        self.session_match = Some(sess.to_owned());
-                        self.result_index = i;
-                        break;
-                    }
+                _ => None,
+            };
+            match bookmark {
+                Some((letter, bookmark @ Bookmark::Pane { .. })) => {
+                    self.pending_bookmarks.push((letter, bookmark));
                 }
+                Some((letter, bookmark)) => {
+                    self.bookmarks.entry(letter).or_insert(bookmark);
+                }
+                None => (),
             }
         }
+        self.attach_bookmarks();
+    }
 
-        if found_next.is_none() {
-            if let Some(i) = first_match {
-                if let Some(sess) = self.sessions.get(i) {
-                    self.session_match = Some(sess.to_owned());
-                    self.result_index = i;
+    /// attach the pane bookmarks read from disk to the panes they
+    /// were saved for, the ones not found yet are tried again
+    /// on the next updates
+    fn attach_bookmarks(&mut self) {
+        if self.pending_bookmarks.is_empty()
+            || self.tab_infos.is_empty()
+            || self.pane_manifest.panes.is_empty()
+        {
+            return;
+        }
+        for (letter, bookmark) in std::mem::take(&mut self.pending_bookmarks) {
+            match (self.resolve_saved_bookmark(&bookmark), bookmark) {
+                (Some((_, Some(id))), Bookmark::Pane { tab, title, .. }) => {
+                    self.bookmarks
+                        .entry(letter)
+                        .or_insert(Bookmark::Pane { id, tab, title });
                 }
+                (_, bookmark) => self.pending_bookmarks.push((letter, bookmark)),
             }
         }
     }
 
-    fn move_up_session(&mut self) {
-        let mut prev_match = None;
-        let mut last_match = None;
-
-        for (i, session) in self.sessions.iter().enumerate() {
-            if self.input == String::default()
-                || self.fz_matcher.fuzzy_match(session, &self.input).is_some()
-            {
-                if i == self.result_index && prev_match.is_some() {
-                    break;
-                }
-                prev_match = Some(i);
-                last_match = Some(i);
-            }
+    /// open a new tab from the selected layout,
+    /// named after the query when there is one
+    fn open_layout_tab(&mut self) {
+        let Some(layout) = self.layout_match.and_then(|i| self.layouts.get(i)).cloned() else {
+            return;
+        };
+        if self.input.is_empty() {
+            self.dismiss();
+        } else {
+            // the new tab takes the focus, stay around
+            // to rename it and close once it is created
+            self.pending_layout_tab = Some((self.input.clone(), self.tab_infos.len()));
         }
+        new_tabs_with_layout_info(layout);
+    }
 
-        if let Some(i) = prev_match {
-            if let Some(sess) = self.sessions.get(i) {
-                self.session_match = Some(sess.to_owned());
-                self.result_index = i;
-            }
+    /// rename the tab opened from a layout and get out of the way
+    fn rename_layout_tab(&mut self) {
+        let Some((name, _)) = self
+            .pending_layout_tab
+            .take_if(|(_, count)| self.tab_infos.len() > *count)
+        else {
             return;
+        };
+        if let Some(tab) = self.tab_infos.iter().find(|t| t.active) {
+            rename_tab(tab.position as u32 + 1, &name);
         }
-        if let Some(i) = last_match {
-            if let Some(sess) = self.sessions.get(i) {
-                self.session_match = Some(sess.to_owned());
-                self.result_index = i;
-            }
+        if self.is_sidebar() {
+            self.reset_query();
+        } else if self.is_hide_on_close() {
+            hide_self();
+            self.reset_for_reuse();
+        } else {
+            // the plugin pane is no longer focused
+            close_self();
         }
     }
 
-    fn fuzzy_find_session(&mut self) {
-        let mut best_score = 0;
+    /// start a new session from the selected layout,
+    /// named after the query when there is one
+    fn start_layout_session(&mut self) {
+        let Some(layout) = self.layout_match.and_then(|i| self.layouts.get(i)).cloned() else {
+            return;
+        };
+        let name = (!self.input.is_empty()).then(|| self.input.clone());
+        self.dismiss();
+        switch_session_with_layout(name.as_deref(), layout, Some(self.cwd.clone()));
+    }
 
-        self.session_match = None;
-        for (i, session) in self.sessions.iter().enumerate() {
-            if let Some(score) = self.fz_matcher.fuzzy_match(session, &self.input) {
-                if score > best_score {
-                    best_score = score;
-                    self.result_index = i;
+    /// focus the tab or session named after the selected project,
+    /// or create it in the project directory.
+    /// `alternate` creates a session instead of a tab (or
+    /// the opposite with `project_open "session"`)
+    fn confirm_project(&mut self, alternate: bool) {
+        let Some(path) = self
+            .project_match
+            .and_then(|i| self.projects.get(i))
+            .cloned()
+        else {
+            return;
+        };
+        let name = project_name(&path).to_string();
+        let as_session = (self.userspace_configuration.get("project_open")
+            == Some(&"session".to_string()))
+            != alternate;
 
-                    self.session_match = Some(session.to_owned());
-                }
-            }
+        let tab = self
+            .tab_infos
+            .iter()
+            .find(|t| t.name == name)
+            .map(|t| t.position);
+        self.dismiss();
+        if let Some(position) = tab {
+            switch_tab_to(position as u32 + 1);
+        } else if self.sessions.contains(&name) {
+            switch_session(Some(&name));
+        } else if as_session {
+            switch_session_with_cwd(Some(&name), Some(PathBuf::from(path)));
+        } else {
+            new_tabs_with_layout(&project_tab_layout(&name, &path));
         }
     }
 
-    fn fuzzy_find_pane(&mut self) {
-        let mut best_score = 0;
+    /// run the built-in command or custom entry at
+    /// index `i` of the palette
+    fn run_palette_item(&mut self, i: usize) {
+        if let Some(command) = COMMANDS.get(i) {
+            // commands apply to the focused pane, so give the focus
+            // back before running them (the ones acting on the
+            // focused pane are not listed in sidebar mode)
+            self.dismiss();
+            (command.run)();
+            return;
+        }
 
-        // reset match
-        self.pane_match = None;
-        self.pane_title_match = String::default();
-        if let Some(p) = self.tab_match {
-            if let Some(panes) = self.pane_manifest.panes.get(&p) {
-                for (i, pane) in panes.iter().enumerate() {
-                    if pane.is_plugin {
-                        continue;
-                    }
-                    if let Some(score) = self
-                        .fz_matcher
-                        .fuzzy_match(pane.title.as_str(), &self.input)
-                    {
-                        if score > best_score {
-                            best_score = score;
-                            self.pane_match = Some(pane.id);
-                            self.pane_title_match = pane.title.to_owned();
-                            self.result_index = i;
-                        }
-                    }
+        let Some(entry) = self.entries.get(i - COMMANDS.len()).cloned() else {
+            return;
+        };
+        match &entry.action {
+            EntryAction::Run(line) => {
+                if let Some(command) = command_to_run(line, &self.cwd) {
+                    self.dismiss();
+                    open_command(command, &entry.name, entry.placement);
                 }
             }
-        }
-    }
-
-    fn get_pane_at_index(&mut self) {
-        if let Some(p) = self.tab_match {
-            if let Some(panes) = self.pane_manifest.panes.get(&p) {
-                for (i, pane) in panes.iter().enumerate() {
-                    if pane.is_plugin {
-                        continue;
-                    }
-                    if (self.input == String::default()
-                        || self
-                            .fz_matcher
-                            .fuzzy_match(pane.title.as_str(), &self.input)
-                            .is_some())
-                        && i == self.result_index
-                    {
-                        self.pane_match = Some(pane.id);
-                        self.pane_title_match = pane.title.to_owned();
-                        self.result_index = i;
-                        break;
+            EntryAction::Open(path) => {
+                let file = FileToOpen {
+                    path: expand_home(path, self.home.as_deref()),
+                    line_number: None,
+                    cwd: Some(self.cwd.clone()),
+                };
+                self.dismiss();
+                match entry.placement {
+                    Placement::Tiled => open_file(file, BTreeMap::new()),
+                    Placement::Floating => open_file_floating(file, None, BTreeMap::new()),
+                    Placement::NewTab => {
+                        new_tabs_with_layout(&file_tab_layout(&entry.name, &file));
                     }
                 }
             }
         }
     }
 
-    fn move_down_pane(&mut self) {
-        let mut first_match = None;
-        let mut seek_result = false;
-        let mut found_next = None;
+    /// command typed after the `>` prefix of the input
+    fn shell_command(&self) -> Option<&str> {
+        self.input.strip_prefix(SHELL_PREFIX).map(str::trim)
+    }
 
-        self.pane_match = None;
-        self.pane_title_match = String::default();
-        if let Some(p) = self.tab_match {
-            if let Some(panes) = self.pane_manifest.panes.get(&p) {
-                for (i, pane) in panes.iter().enumerate() {
-                    if pane.is_plugin {
-                        continue;
-                    }
-                    if self.input == String::default()
-                        || self
-                            .fz_matcher
-                            .fuzzy_match(pane.title.as_str(), &self.input)
-                            .is_some()
-                    {
-                        if first_match.is_none() {
-                            first_match = Some(i);
-                        }
+    /// run the typed shell command in a new command pane
+    /// using the plugin's initial working directory
+    fn run_shell_command(&mut self, placement: Placement) {
+        let Some(command) = self
+            .shell_command()
+            .and_then(|line| command_to_run(line, &self.cwd))
+        else {
+            return;
+        };
 
-                        if i == self.result_index {
-                            seek_result = true;
-                            continue;
-                        }
+        // the new pane takes the focus, so get
+        // out of the way before opening it
+        self.dismiss();
+        let name = command.path.to_string_lossy().to_string();
+        open_command(command, &name, placement);
+    }
 
-                        if seek_result {
-                            self.pane_match = Some(pane.id);
-                            self.pane_title_match = pane.title.to_owned();
-                            found_next = Some(i);
-                            self.result_index = i;
-                            break;
-                        }
-                    }
-                }
+    /// switch the selected tab to its next (or previous) swap layout.
+    /// Swap layouts only apply to the active tab, so another
+    /// tab is focused first. Pathfinder gets out of the way
+    /// first, zellij cycles the floating swap layouts while
+    /// a floating pane is focused
+    fn cycle_swap_layout(&mut self, forward: bool) {
+        if self.current_view != View::Tab {
+            return;
+        }
+        let Some(tab) = self.tab_match.and_then(|p| self.tab_infos.get(p)) else {
+            return;
+        };
 
-                if found_next.is_none() {
-                    if let Some(i) = first_match {
-                        if let Some(pane) = panes.get(i) {
-                            self.pane_match = Some(pane.id);
-                            self.pane_title_match = pane.title.to_owned();
-                            self.result_index = i;
-                        }
-                    }
-                }
-            }
+        let switch_to = (!tab.active).then_some(tab.position);
+        self.dismiss();
+        if let Some(position) = switch_to {
+            switch_tab_to(position as u32 + 1);
+        }
+        if forward {
+            next_swap_layout();
+        } else {
+            previous_swap_layout();
         }
     }
 
-    fn move_up_pane(&mut self) {
-        let mut prev_match = None;
-        let mut last_match = None;
-
-        self.pane_match = None;
-        self.pane_title_match = String::default();
-        if let Some(p) = self.tab_match {
-            if let Some(panes) = self.pane_manifest.panes.get(&p) {
-                for (i, pane) in panes.iter().enumerate() {
-                    if pane.is_plugin {
-                        continue;
-                    }
-                    if self.input == String::default()
-                        || self
-                            .fz_matcher
-                            .fuzzy_match(pane.title.as_str(), &self.input)
-                            .is_some()
-                    {
-                        if i == self.result_index && prev_match.is_some() {
-                            break;
-                        }
-                        prev_match = Some(i);
-                        last_match = Some(i);
-                    }
-                }
+    /// first step of moving a pane: remember the selected
+    /// pane and pick the destination in the Tab view
+    fn start_move_pane(&mut self) {
+        if self.current_view != View::Pane {
+            return;
+        }
 
-                if let Some(i) = prev_match {
-                    if let Some(pane) = panes.get(i) {
-                        self.pane_match = Some(pane.id);
-                        self.pane_title_match = pane.title.to_owned();
-                        self.result_index = i;
-                    }
-                    return;
-                }
+        // marked panes are moved together
+        let moving: Vec<(u32, String)> = if self.marked_panes.is_empty() {
+            self.pane_match
+                .map(|id| (id, self.pane_title_match.to_owned()))
+                .into_iter()
+                .collect()
+        } else {
+            self.pane_manifest
+                .panes
+                .values()
+                .flatten()
+                .filter(|pane| !pane.is_plugin && self.marked_panes.contains(&pane.id))
+                .map(|pane| (pane.id, pane.title.to_owned()))
+                .collect()
+        };
 
-                if let Some(i) = last_match {
-                    if let Some(pane) = panes.get(i) {
-                        self.pane_match = Some(pane.id);
-                        self.pane_title_match = pane.title.to_owned();
-                        self.result_index = i;
-                    }
-                }
-            }
+        if !moving.is_empty() {
+            self.moving_panes = moving;
+            self.set_view(View::Tab);
         }
     }
 
-    /// remove_input_at_index  removes char at the
-    /// cursor index and update input.
-    /// Returns true if the input has change
-    fn remove_input_at_index(&mut self) -> bool {
-        if self.input.is_empty() {
-            self.input.pop();
-        } else if self.input_cusror_index > 0 && self.input_cusror_index <= self.input.len() {
-            self.input.remove(self.input_cusror_index - 1);
-            // update cursor index
-            self.input_cusror_index -= 1;
-
-            return true;
-        } else if self.input_cusror_index == 0 {
-            self.input.remove(0);
-        }
-        false
+    fn cancel_move_pane(&mut self) {
+        self.moving_panes.clear();
+        self.set_view(View::Pane);
     }
 
-    /// remove_input_at_index  removes char at the
-    /// cursor index and update input.
-    /// Returns true if the input has change
-    fn insert_input_at_index(&mut self, c: char) -> bool {
-        if self.input.is_empty() {
-            self.input.push(c);
+    /// move the pane to the selected tab, or to a new tab
+    /// named after the query when no tab matches it
+    fn move_pane_to_tab(&mut self) {
+        if !self.input.is_empty() && !self.tab_infos.iter().any(|t| self.tab_matches_query(t)) {
+            self.move_pane_to_new_tab();
+            return;
+        }
 
-            // update cursor index
-            self.input_cusror_index += 1;
-            return true;
-        } else if self.input_cusror_index > 0 && self.input_cusror_index <= self.input.len() {
-            self.input.insert(self.input_cusror_index, c);
-            // update cursor index
-            self.input_cusror_index += 1;
+        let Some(position) = self
+            .tab_match
+            .and_then(|m| self.tab_infos.get(m))
+            .map(|t| t.position)
+        else {
+            return;
+        };
+        let pane_ids = self.take_moving_panes();
+        self.dismiss();
+        break_panes_to_tab_with_index(&pane_ids, position, true);
+    }
 
-            return true;
-        } else if self.input_cusror_index == 0 {
-            self.input.insert(0, c);
-            self.input_cusror_index += 1;
+    /// move the pane to a new tab named after the query
+    fn move_pane_to_new_tab(&mut self) {
+        let name = Some(self.input.to_owned()).filter(|n| !n.is_empty());
+        let pane_ids = self.take_moving_panes();
+        if pane_ids.is_empty() {
+            return;
         }
-        false
+        self.dismiss();
+        break_panes_to_new_tab(&pane_ids, name, true);
     }
 
-    /// print the input prompt
-    fn print_prompt(&self, _rows: usize, _cols: usize) {
-        // if not enough space in UI
-        // input prompt
-        let prompt = " > ".cyan().bold().to_string();
-        if self.input.is_empty() {
-            println!(
-                "{} {}{}",
-                prompt,
-                "┃".bold().white(),
-                "search pattern".dimmed().italic(),
-            );
-        } else {
-            self.print_non_empty_input_prompt(prompt);
-        }
+    fn take_moving_panes(&mut self) -> Vec<PaneId> {
+        self.marked_panes.clear();
+        std::mem::take(&mut self.moving_panes)
+            .into_iter()
+            .map(|(id, _)| PaneId::Terminal(id))
+            .collect()
     }
 
-    fn print_non_empty_input_prompt(&self, prompt: String) {
-        match self.input_cusror_index.cmp(&self.input.len()) {
-            std::cmp::Ordering::Equal => {
-                println!("{} {}{}", prompt, self.input.dimmed(), "┃".bold().white(),);
+    /// mark or unmark the selected entry of the current view
+    fn toggle_mark(&mut self) {
+        match self.current_view {
+            View::Tab => {
+                if let Some(t) = self.tab_match.and_then(|m| self.tab_infos.get(m)) {
+                    toggle(&mut self.marked_tabs, t.position);
+                }
             }
-            std::cmp::Ordering::Less => {
-                let copy = self.input.clone();
-                let (before_curs, after_curs) = copy.split_at(self.input_cusror_index);
-
-                println!(
-                    "{} {}{}{}",
-                    prompt,
-                    before_curs.dimmed(),
-                    "┃".bold().white(),
-                    after_curs.dimmed()
-                );
+            View::Pane => {
+                if let Some(pane_id) = self.pane_match {
+                    toggle(&mut self.marked_panes, pane_id);
+                }
             }
-
-            std::cmp::Ordering::Greater => (),
+            View::Session => {
+                if let Some(sess) = self.session_match.clone() {
+                    toggle(&mut self.marked_sessions, sess);
+                }
+            }
+            View::Command | View::Entry | View::Project | View::Layout | View::Bookmark => (),
         }
     }
-}
-
-register_plugin!(State);
-impl ZellijPlugin for State {
-    fn load(&mut self, configuration: BTreeMap<String, String>) {
-        self.userspace_configuration = configuration;
 
-        // Permission
-        // - ReadApplicationState => for Tab and Pane update
-        // - ChangeApplicationState => rename plugin pane, close managed paned
-        request_permission(&[
-            PermissionType::ReadApplicationState,
-            PermissionType::ChangeApplicationState,
-        ]);
-        subscribe(&[
-            EventType::ModeUpdate,
-            EventType::TabUpdate,
-            EventType::PaneUpdate,
-            EventType::Key,
-            EventType::SessionUpdate,
-        ]);
+    /// number of marked entries in the current view
+    fn marked_count(&self) -> usize {
+        match self.current_view {
+            View::Tab => self.marked_tabs.len(),
+            View::Pane => self.marked_panes.len(),
+            View::Session => self.marked_sessions.len(),
+            View::Command | View::Entry | View::Project | View::Layout | View::Bookmark => 0,
+        }
+    }
 
-        rename_plugin_pane(get_plugin_ids().plugin_id, "PathFinder");
+    /// " (n marked)" when entries are marked in the current view,
+    /// undo hint after a rename and invalid custom entries
+    fn list_title_suffix(&self) -> String {
+        let mut suffix = match self.marked_count() {
+            0 => String::default(),
+            n => format!(" ({} marked)", n),
+        };
+        if self.undo_rename.is_some() {
+            suffix.push_str(" (Ctrl + z to undo rename)");
+        }
+        if self.read_only() {
+            let read_only = " (read-only, Alt + r to ask for permissions)";
+            suffix.push_str(&self.theme.muted.paint(read_only).to_string());
+        }
+        if matches!(self.current_view, View::Command | View::Entry) && !self.entry_errors.is_empty()
+        {
+            let invalid = format!(" (invalid entries: {})", self.entry_errors.join(", "));
+            suffix.push_str(&self.theme.alert.paint(invalid).to_string());
+        }
+        if let Some(name) = &self.selection_lost {
+            let lost = format!(" (\"{}\" is gone)", name);
+            suffix.push_str(&self.theme.alert.paint(lost).to_string());
+        }
+        suffix
     }
 
-    fn update(&mut self, event: Event) -> bool {
-        let mut should_render = true;
-        match event {
-            Event::TabUpdate(tab_info) => {
-                self.tab_infos = tab_info;
-                self.get_focused_tab();
-                should_render = true;
-            }
-            Event::PaneUpdate(pane_manifest) => {
-                self.pane_manifest = pane_manifest;
-                should_render = true;
+    /// close marked panes or tabs, kill marked sessions,
+    /// or the selected entry if nothing is marked
+    fn close_marked(&mut self) {
+        let action = match self.current_view {
+            View::Tab => {
+                let positions: Vec<usize> = if self.marked_tabs.is_empty() {
+                    self.tab_match
+                        .and_then(|m| self.tab_infos.get(m))
+                        .map(|t| t.position)
+                        .into_iter()
+                        .collect()
+                } else {
+                    self.marked_tabs.iter().copied().collect()
+                };
+                PendingAction::CloseTabs(positions)
             }
-            Event::SessionUpdate(session_infos, _) => {
-                self.sessions = session_infos
-                    .into_iter()
-                    .map(|session_info| session_info.name)
-                    .collect();
-                // self.sessions = session_infos;
+            View::Pane => {
+                let pane_ids: Vec<u32> = if self.marked_panes.is_empty() {
+                    self.pane_match.into_iter().collect()
+                } else {
+                    self.marked_panes.iter().copied().collect()
+                };
+                PendingAction::ClosePanes(pane_ids)
             }
+            View::Session => {
+                let sessions: Vec<String> = if self.marked_sessions.is_empty() {
+                    self.session_match.clone().into_iter().collect()
+                } else {
+                    self.marked_sessions.iter().cloned().collect()
+                };
 
-            Event::Key(key) => {
-                should_render = self.handle_key_event(key);
+                // never kill the session the plugin is running in
+                PendingAction::KillSessions(
+                    sessions
+                        .into_iter()
+                        .filter(|s| Some(s) != self.current_session.as_ref())
+                        .collect(),
+                )
             }
-            _ => (),
+            View::Bookmark => {
+                // bookmarks are removed without confirmation
+                let letter = self
+                    .bookmark_match
+                    .and_then(|i| self.bookmarks.keys().nth(i))
+                    .copied();
+                if let Some(letter) = letter {
+                    self.bookmarks.remove(&letter);
+                    self.save_bookmarks();
+                    self.set_view(if self.bookmarks.is_empty() {
+                        View::Tab
+                    } else {
+                        View::Bookmark
+                    });
+                }
+                return;
+            }
+            View::Command | View::Entry | View::Project | View::Layout => return,
         };
 
-        should_render
+        if action.is_empty() {
+            return;
+        }
+        if self.confirm_destructive() {
+            self.overlay = Some(Overlay::Confirm(action));
+        } else {
+            self.run_action(action);
+        }
     }
 
-    fn render(&mut self, rows: usize, cols: usize) {
-        // get the shell args from config
-
-        let debug = self.userspace_configuration.get("debug");
-        // count keep tracks of lines printed
-        // 4 lines for CWD and keybinding views
-        let mut count = 4;
+    /// ask for confirmation before closing or killing, enabled by default
+    fn confirm_destructive(&self) -> bool {
+        self.userspace_configuration
+            .get("confirm_destructive")
+            .is_none_or(|x| x != "false")
+    }
 
-        match self.current_view {
-            View::Tab => {
-                print_ribbon_with_coordinates(
-                    Text::new("Tabs Selector").selected(),
-                    1,
-                    0,
-                    None,
-                    None,
-                );
-                print_ribbon_with_coordinates(Text::new("Panes Selector"), 18, 0, None, None);
-                print_ribbon_with_coordinates(Text::new("Sessions Selector"), 36, 0, None, None);
-                println!();
-                println!();
+    fn run_action(&mut self, action: PendingAction) {
+        match action {
+            PendingAction::CloseTabs(mut positions) => {
+                // close from the last tab so positions do not shift
+                positions.sort_unstable_by(|a, b| b.cmp(a));
+                for position in positions {
+                    close_tab_with_index(position);
+                }
+                self.marked_tabs.clear();
+                self.tab_match = None;
             }
-
-            View::Pane => {
-                print_ribbon_with_coordinates(Text::new("Tabs Selector"), 1, 0, None, None);
-                print_ribbon_with_coordinates(
-                    Text::new("Panes Selector").selected(),
-                    18,
-                    0,
-                    None,
-                    None,
-                );
-                print_ribbon_with_coordinates(Text::new("Sessions Selector"), 36, 0, None, None);
-                println!();
-                println!();
+            PendingAction::ClosePanes(pane_ids) => {
+                for pane_id in pane_ids {
+                    close_terminal_pane(pane_id);
+                }
+                self.marked_panes.clear();
+                self.pane_match = None;
+                self.pane_title_match = String::default();
             }
-            View::Session => {
-                print_ribbon_with_coordinates(Text::new("Tabs Selector"), 1, 0, None, None);
-
-                print_ribbon_with_coordinates(Text::new("Panes Selector"), 18, 0, None, None);
-                print_ribbon_with_coordinates(
-                    Text::new("Sessions Selector").selected(),
-                    36,
-                    0,
-                    None,
-                    None,
-                );
-                println!();
-                println!();
+            PendingAction::KillSessions(sessions) => {
+                kill_sessions(&sessions);
+                self.marked_sessions.clear();
+                self.session_match = None;
             }
         }
+    }
 
-        count += 1;
+    /// keys of the overlay displayed over the current view
+    fn handle_overlay_key_event(&mut self, key: KeyWithModifier) -> bool {
+        let Some(overlay) = self.overlay.take() else {
+            return false;
+        };
 
-        self.print_prompt(rows, cols);
-        count += 1;
+        match overlay {
+            Overlay::Confirm(action) => match key.bare_key {
+                BareKey::Char('y') | BareKey::Char('Y') | BareKey::Enter => {
+                    self.run_action(action);
+                }
+                BareKey::Char('n') | BareKey::Char('N') | BareKey::Esc => (),
+                _ => self.overlay = Some(Overlay::Confirm(action)),
+            },
+            Overlay::Rename(target, mut name) => match key.bare_key {
+                BareKey::Enter => {
+                    if !name.is_empty() {
+                        self.apply_rename(target, name);
+                    }
+                }
+                BareKey::Esc => (),
+                BareKey::Backspace => {
+                    name.pop();
+                    self.overlay = Some(Overlay::Rename(target, name));
+                }
+                BareKey::Char(c) if !key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                    name.push(c);
+                    self.overlay = Some(Overlay::Rename(target, name));
+                }
+                _ => self.overlay = Some(Overlay::Rename(target, name)),
+            },
+            Overlay::Annotate(target, mut text) => match key.bare_key {
+                BareKey::Enter => self.set_annotation(target, text),
+                BareKey::Esc => (),
+                BareKey::Backspace => {
+                    text.pop();
+                    self.overlay = Some(Overlay::Annotate(target, text));
+                }
+                BareKey::Char(c) if !key.has_modifiers(&[KeyModifier::Ctrl]) => {
+                    text.push(c);
+                    self.overlay = Some(Overlay::Annotate(target, text));
+                }
+                _ => self.overlay = Some(Overlay::Annotate(target, text)),
+            },
+            Overlay::Bookmark => {
+                if let (BareKey::Char(letter), Some(bookmark)) =
+                    (key.bare_key, self.selected_bookmark())
+                {
+                    if letter.is_ascii_alphanumeric() {
+                        self.set_bookmark(letter, bookmark);
+                    }
+                }
+            }
+            Overlay::Jump => {
+                if let BareKey::Char(letter) = key.bare_key {
+                    self.jump_to_bookmark(letter);
+                }
+            }
+        }
+        true
+    }
 
-        match self.current_view {
-            View::Tab => {
-                println!("Tabs: ");
+    /// rename the selected tab or pane
+    fn start_rename(&mut self) {
+        let rename = match self.current_view {
+            View::Tab => self
+                .tab_match
+                .and_then(|m| self.tab_infos.get(m))
+                .map(|t| (RenameTarget::Tab(t.position), t.name.to_owned())),
+            View::Pane => self
+                .pane_match
+                .map(|id| (RenameTarget::Pane(id), self.pane_title_match.to_owned())),
+            View::Session
+            | View::Command
+            | View::Entry
+            | View::Project
+            | View::Layout
+            | View::Bookmark => None,
+        };
 
-                count += 1;
+        if let Some((target, name)) = rename {
+            self.overlay = Some(Overlay::Rename(target, name));
+        }
+    }
 
-                for (i, t) in self.tab_infos.iter().enumerate() {
-                    if self
-                        .fz_matcher
-                        .fuzzy_match(t.name.as_str(), &self.input)
-                        .is_some()
-                    {
-                        // limits display of completion
-                        // based on available rows in pane
-                        // with arbitrary buffer for safety
-                        if count >= rows - 4 {
-                            println!(" - {}", "...".dimmed());
-                            break;
-                        }
+    fn apply_rename(&mut self, target: RenameTarget, name: String) {
+        let previous = match target {
+            RenameTarget::Tab(position) => self
+                .tab_infos
+                .iter()
+                .find(|t| t.position == position)
+                .map(|t| t.name.to_owned()),
+            RenameTarget::Pane(id) => self
+                .pane_manifest
+                .panes
+                .values()
+                .flatten()
+                .find(|p| !p.is_plugin && p.id == id)
+                .map(|p| p.title.to_owned()),
+        };
 
-                        if i == self.result_index {
-                            println!(" - {}", t.name.blue().bold());
-                        } else {
-                            println!(" - {}", t.name.dimmed());
-                        }
+        rename_target(target, &name);
 
-                        count += 1;
-                    }
+        // tab annotations follow the tab name
+        if let (RenameTarget::Tab(_), Some(previous)) = (target, &previous) {
+            if let Some(text) = self
+                .annotations
+                .remove(&Annotated::Tab(previous.to_owned()))
+            {
+                self.annotations
+                    .insert(Annotated::Tab(name.to_owned()), text);
+                self.save_annotations();
+            }
+        }
+
+        // allow undo for a short time
+        if let Some(previous) = previous {
+            self.undo_rename = Some((target, previous));
+            self.undo_timers += 1;
+            set_timeout(UNDO_RENAME_SECONDS);
+        }
+    }
+
+    /// restore the name before the last rename
+    fn undo_last_rename(&mut self) {
+        if let Some((target, previous)) = self.undo_rename.take() {
+            rename_target(target, &previous);
+        }
+    }
+
+    /// maximum number of queries remembered per view
+    fn history_length(&self) -> usize {
+        self.userspace_configuration
+            .get("history_length")
+            .and_then(|l| l.parse().ok())
+            .unwrap_or(DEFAULT_HISTORY_LENGTH)
+    }
+
+    /// add the confirmed query to the history of the current view
+    fn remember_query(&mut self) {
+        let length = self.history_length();
+        if self.input.is_empty() || length == 0 {
+            return;
+        }
+        let history = self.history.entry(self.current_view).or_default();
+        history.retain(|q| *q != self.input);
+        history.push(self.input.clone());
+        if history.len() > length {
+            history.drain(..history.len() - length);
+        }
+        self.save_history();
+        self.history_pos = None;
+    }
+
+    /// Up recalls queries when browsing the history, or from
+    /// the top of the list with an empty query
+    fn can_recall_query(&self) -> bool {
+        let at_top = self
+            .list_entries
+            .first()
+            .is_none_or(|i| *i == self.result_index);
+        let has_history = self
+            .history
+            .get(&self.current_view)
+            .is_some_and(|h| !h.is_empty());
+        has_history && (self.history_pos.is_some() || (self.input.is_empty() && at_top))
+    }
+
+    fn recall_older_query(&mut self) {
+        let Some(history) = self.history.get(&self.current_view) else {
+            return;
+        };
+        let pos = match self.history_pos {
+            Some(pos) => pos.saturating_sub(1),
+            None => history.len().saturating_sub(1),
+        };
+        if let Some(query) = history.get(pos).cloned() {
+            self.history_pos = Some(pos);
+            self.set_input(query);
+        }
+    }
+
+    /// recall the next query, back to an empty
+    /// query after the most recent one
+    fn recall_newer_query(&mut self) {
+        let Some(pos) = self.history_pos else {
+            return;
+        };
+        let next = self
+            .history
+            .get(&self.current_view)
+            .and_then(|h| h.get(pos + 1))
+            .cloned();
+        self.history_pos = next.as_ref().map(|_| pos + 1);
+        self.set_input(next.unwrap_or_default());
+    }
+
+    /// replace the query and update the selection
+    fn set_input(&mut self, input: String) {
+        self.input_cusror_index = input.len();
+        self.input = input;
+        self.fuzzy_find();
+    }
+
+    fn save_history(&self) {
+        let lines: Vec<String> = View::ALL
+            .into_iter()
+            .filter_map(|view| Some((view, self.history.get(&view)?)))
+            .flat_map(|(view, queries)| {
+                queries
+                    .iter()
+                    .map(move |q| format!("{}\t{}", view.id(), escape_field(q)))
+            })
+            .collect();
+        let _ = std::fs::write(PathBuf::from(DATA_DIR).join("history"), lines.join("\n"));
+    }
+
+    fn load_history(&mut self) {
+        let Ok(content) = std::fs::read_to_string(PathBuf::from(DATA_DIR).join("history")) else {
+            return;
+        };
+        for line in content.lines() {
+            let Some((name, query)) = line.split_once('\t') else {
+                continue;
+            };
+            if let Some(view) = View::ALL.iter().find(|v| v.id() == name) {
+                self.history
+                    .entry(*view)
+                    .or_default()
+                    .push(unescape_field(query));
+            }
+        }
+
+        // the configured length may have been lowered
+        let length = self.history_length();
+        for history in self.history.values_mut() {
+            if history.len() > length {
+                history.drain(..history.len() - length);
+            }
+        }
+    }
+
+    /// ReadApplicationState => for Tab and Pane update
+    fn request_read_permission(&mut self) {
+        self.permissions = Permissions::RequestingRead;
+        request_permission(&[PermissionType::ReadApplicationState]);
+    }
+
+    /// - ReadApplicationState => asked again, zellij only stores the
+    ///   last granted set so it must contain every permission
+    /// - ChangeApplicationState => rename plugin pane, close managed paned
+    /// - OpenTerminalsOrPlugins => new panes from the command palette
+    /// - RunCommands => command panes from the `>` prompt
+    /// - OpenFiles => files opened by custom entries
+    /// - ReadCliPipes => show hidden plugin from `zellij pipe`
+    fn request_action_permissions(&self) {
+        let mut permissions = vec![
+            PermissionType::ReadApplicationState,
+            PermissionType::ChangeApplicationState,
+            PermissionType::OpenTerminalsOrPlugins,
+            PermissionType::RunCommands,
+            PermissionType::OpenFiles,
+        ];
+        if self.is_hide_on_close() {
+            permissions.push(PermissionType::ReadCliPipes);
+        }
+        request_permission(&permissions);
+    }
+
+    /// whether the action permissions are granted
+    fn can_act(&self) -> bool {
+        self.permissions == Permissions::Granted
+    }
+
+    /// whether the session can only be browsed, the
+    /// action permissions being denied
+    fn read_only(&self) -> bool {
+        self.permissions == Permissions::ReadOnly
+    }
+
+    /// get out of the way before acting on a confirmed selection
+    fn dismiss(&mut self) {
+        self.remember_query();
+        if self.is_sidebar() {
+            self.input = String::default();
+            self.input_cusror_index = 0;
+        } else if self.is_hide_on_close() {
+            hide_self();
+            self.reset_for_reuse();
+        } else {
+            close_focus();
+        }
+    }
+
+    fn move_down(&mut self) {
+        match self.current_view {
+            View::Tab => self.move_down_tab(),
+            View::Pane => self.move_down_pane(),
+            View::Session => self.move_down_session(),
+            View::Command | View::Entry => self.move_down_command(),
+            View::Project => self.move_down_project(),
+            View::Layout => self.move_down_layout(),
+            View::Bookmark => self.move_down_bookmark(),
+        }
+    }
+
+    fn move_up(&mut self) {
+        match self.current_view {
+            View::Tab => self.move_up_tab(),
+            View::Pane => self.move_up_pane(),
+            View::Session => self.move_up_session(),
+            View::Command | View::Entry => self.move_up_command(),
+            View::Project => self.move_up_project(),
+            View::Layout => self.move_up_layout(),
+            View::Bookmark => self.move_up_bookmark(),
+        }
+    }
+
+    /// select the entry at index in the current view
+    fn select_index(&mut self, idx: usize) {
+        match self.current_view {
+            View::Tab => self.seek_tab(idx),
+            View::Pane => {
+                self.result_index = idx;
+                self.get_pane_at_index();
+            }
+            View::Session => {
+                if let Some(sess) = self.sessions.get(idx) {
+                    self.session_match = Some(sess.to_owned());
+                    self.result_index = idx;
                 }
-                println!();
-                if let Some(m) = self.tab_match {
-                    if let Some(t) = self.tab_infos.get(m) {
-                        println!(
-                            "{} {}",
-                            color_bold(WHITE, "Selected Tab ->"),
-                            t.name.as_str().blue().bold()
-                        );
-                    }
-                } else {
-                    println!(
-                        "{} {}",
-                        color_bold(WHITE, "Selected Tab ->"),
-                        "No matches found".dimmed()
-                    );
+            }
+            View::Command | View::Entry => {
+                if self.palette().iter().any(|(i, _)| *i == idx) {
+                    self.command_match = Some(idx);
+                    self.result_index = idx;
                 }
             }
-            View::Pane => {
-                println!("Panes: ");
-                if let Some(p) = self.tab_match {
-                    if let Some(panes) = self.pane_manifest.panes.get(&p) {
-                        for (i, pane) in panes.iter().enumerate() {
-                            if !pane.is_plugin
-                                && self
-                                    .fz_matcher
-                                    .fuzzy_match(pane.title.as_str(), &self.input)
-                                    .is_some()
-                            {
-                                // limits display of completion
-                                // based on available rows in pane
-                                // with arbitrary buffer for safety
-                                if count >= rows - 4 {
-                                    println!(" - {}", "...".dimmed());
-                                    break;
-                                }
-                                if i == self.result_index {
-                                    println!(" - {}", pane.title.blue().bold());
-                                } else {
-                                    println!(" - {}", pane.title.dimmed());
-                                }
-                                count += 1;
-                            }
-                        }
-                    }
+            View::Project => {
+                if idx < self.projects.len() {
+                    self.project_match = Some(idx);
+                    self.result_index = idx;
                 }
+            }
+            View::Layout => {
+                if idx < self.layouts.len() {
+                    self.layout_match = Some(idx);
+                    self.result_index = idx;
+                }
+            }
+            View::Bookmark => {
+                if idx < self.bookmarks.len() {
+                    self.bookmark_match = Some(idx);
+                    self.result_index = idx;
+                }
+            }
+        }
+    }
 
-                println!();
-                if !self.pane_title_match.is_empty() {
-                    println!(
-                        "{} {}",
-                        color_bold(WHITE, "Selected Pane ->"),
-                        self.pane_title_match.as_str().blue().bold()
-                    );
-                } else {
-                    println!(
-                        "{} {}",
-                        color_bold(WHITE, "Selected Pane ->"),
-                        "No matches found".dimmed()
-                    );
+    /// keep the selection and the marks on the same tabs after
+    /// a TabUpdate: tabs are found by name, or at the same position
+    /// when renamed. The selection falls back to the focused tab
+    /// when the selected tab was closed
+    fn resolve_tabs(&mut self, previous: &[TabInfo]) {
+        let resolve = |tab: &TabInfo| {
+            let same_name = self
+                .tab_infos
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| t.name == tab.name)
+                .min_by_key(|(_, t)| t.position.abs_diff(tab.position))
+                .map(|(i, _)| i);
+            same_name.or_else(|| {
+                if self.tab_infos.len() < previous.len() {
+                    return None;
                 }
+                let (i, renamed) = self
+                    .tab_infos
+                    .iter()
+                    .enumerate()
+                    .find(|(_, t)| t.position == tab.position)?;
+                (!previous.iter().any(|p| p.name == renamed.name)).then_some(i)
+            })
+        };
 
-                if let Some(m) = self.tab_match {
-                    if let Some(t) = self.tab_infos.get(m) {
-                        println!(
-                            "{} {}",
-                            color_bold(WHITE, "Selected Tab ->"),
-                            t.name.as_str().blue().bold()
-                        );
-                    }
-                } else {
-                    println!(
-                        "{} {}",
-                        color_bold(WHITE, "Selected Tab ->"),
-                        "No matches found".dimmed()
-                    );
+        self.marked_tabs = self
+            .marked_tabs
+            .iter()
+            .filter_map(|position| previous.iter().find(|t| t.position == *position))
+            .filter_map(&resolve)
+            .filter_map(|i| self.tab_infos.get(i).map(|t| t.position))
+            .collect();
+
+        let Some(selected) = self.tab_match.and_then(|i| previous.get(i)) else {
+            return;
+        };
+        match resolve(selected) {
+            Some(i) => {
+                self.tab_match = Some(i);
+                if let View::Tab = self.current_view {
+                    self.result_index = i;
+                }
+            }
+            None => {
+                // selected again by get_focused_tab
+                if let View::Tab = self.current_view {
+                    self.selection_lost = Some(selected.name.to_owned());
                 }
+                self.tab_match = None;
             }
+        }
+    }
 
-            View::Session => {
-                println!("Sessions: ");
-                for (i, session) in self.sessions.iter().enumerate() {
-                    if self.fz_matcher.fuzzy_match(session, &self.input).is_some() {
-                        // limits display of completion
-                        // based on available rows in pane
-                        // with arbitrary buffer for safety
-                        if count >= rows - 4 {
-                            println!(" - {}", "...".dimmed());
-                            break;
-                        }
+    /// keep the selection on the same pane after an update,
+    /// and forget the panes that were closed
+    fn resolve_panes(&mut self) {
+        let exists = |id: u32| {
+            self.pane_manifest
+                .panes
+                .values()
+                .flatten()
+                .any(|p| !p.is_plugin && p.id == id)
+        };
+        let marked: BTreeSet<u32> = self
+            .marked_panes
+            .iter()
+            .copied()
+            .filter(|id| exists(*id))
+            .collect();
+        let moving: Vec<(u32, String)> = self
+            .moving_panes
+            .iter()
+            .filter(|(id, _)| exists(*id))
+            .cloned()
+            .collect();
+        self.marked_panes = marked;
+        self.moving_panes = moving;
 
-                        if i == self.result_index {
-                            println!(" - {}", session.blue().bold());
-                        } else {
-                            println!(" - {}", session.dimmed());
-                        }
+        if self.current_view != View::Pane {
+            return;
+        }
+        let Some(id) = self.pane_match else {
+            return;
+        };
+        let found = self
+            .scoped_panes()
+            .into_iter()
+            .enumerate()
+            .find(|(_, (_, p))| !p.is_plugin && p.id == id)
+            .map(|(i, (_, p))| (i, p.title.to_owned()));
+        match found {
+            Some((i, title)) => {
+                self.result_index = i;
+                self.pane_title_match = title;
+            }
+            None => {
+                self.selection_lost = Some(std::mem::take(&mut self.pane_title_match));
+                self.pane_match = None;
+                self.result_index = self.pane_rows().first().copied().unwrap_or(0);
+                self.get_pane_at_index();
+            }
+        }
+    }
 
-                        count += 1;
-                    }
+    /// keep the selection on the same session after a SessionUpdate
+    fn resolve_sessions(&mut self) {
+        self.marked_sessions = self
+            .marked_sessions
+            .iter()
+            .filter(|s| self.sessions.contains(s))
+            .cloned()
+            .collect();
+
+        let Some(session) = self.session_match.clone() else {
+            return;
+        };
+        match self.sessions.iter().position(|s| *s == session) {
+            Some(i) => {
+                if let View::Session = self.current_view {
+                    self.result_index = i;
+                }
+            }
+            None => {
+                self.session_match = None;
+                if let View::Session = self.current_view {
+                    self.selection_lost = Some(session);
+                    self.move_down_session();
                 }
+            }
+        }
+    }
 
-                println!();
+    /// close current plugins and its hepler pane
+    /// get the focused tab position
+    fn get_focused_tab(&mut self) {
+        for (i, t) in self.tab_infos.iter().enumerate() {
+            if t.active {
+                self.focus_tab_pos = t.position;
+                if self.tab_match.is_none() {
+                    self.tab_match = Some(i);
 
-                if let Some(sess) = &self.session_match {
-                    println!(
-                        "{} {}",
-                        color_bold(WHITE, "Selected Session ->"),
-                        sess.as_str().blue().bold()
-                    );
-                } else {
-                    println!(
-                        "{} {}",
-                        color_bold(WHITE, "Selected Session ->"),
-                        "No matches found".dimmed()
-                    );
+                    if let View::Tab = self.current_view {
+                        self.result_index = i;
+                    }
                 }
             }
         }
+    }
 
-        // Key binding view
+    fn close(&mut self) {
+        if self.is_sidebar() {
+            // sidebar never closes, only reset the query
+            self.reset_query();
+            return;
+        }
+        if self.is_hide_on_close() {
+            hide_self();
+            self.reset_for_reuse();
+            return;
+        }
+        if self.can_act() {
+            close_plugin_pane(get_plugin_ids().plugin_id);
+        } else {
+            // closing another pane needs ChangeApplicationState
+            close_self();
+        }
+    }
+
+    /// hide the plugin pane instead of closing it,
+    /// so that the next invocation is instant
+    fn is_hide_on_close(&self) -> bool {
+        self.config_flag("hide_on_close")
+    }
 
-        if debug.is_some_and(|x| x == "true") {
-            println!("input: {}", self.input);
+    /// reset the query and selection while hidden,
+    /// keeping the tab, pane and session state
+    fn reset_for_reuse(&mut self) {
+        self.input = String::default();
+        self.input_cusror_index = 0;
+        self.current_view = View::Tab;
+        self.result_index = 0;
+        // selected on next TabUpdate
+        self.tab_match = None;
+        self.pane_match = None;
+        self.pane_title_match = String::default();
+        self.session_match = None;
+        self.moving_panes.clear();
+        self.marked_tabs.clear();
+        self.marked_panes.clear();
+        self.marked_sessions.clear();
+        self.overlay = None;
+        self.history_pos = None;
+        self.view_states.clear();
+        self.exit_hint_mode();
+    }
 
-            println!("Cursor: {}", self.input_cusror_index);
-            println!("len: {}", self.input.len());
+    /// boolean plugin configuration option
+    fn config_flag(&self, key: &str) -> bool {
+        self.userspace_configuration
+            .get(key)
+            .is_some_and(|x| x == "true")
+    }
 
-            println!("tab match: {}", self.tab_match.unwrap_or(42));
-            println!("pane match: {}", self.pane_match.unwrap_or(42));
-            println!("focussed tab : {}", self.focus_tab_pos);
+    /// persistent sidebar mode, launched as a tiled pane in a layout
+    fn is_sidebar(&self) -> bool {
+        self.config_flag("sidebar")
+    }
 
-            println!("result_index: {}", self.result_index);
+    /// clear the query and go back to the focused tab/pane
+    fn reset_query(&mut self) {
+        self.input = String::default();
+        self.input_cusror_index = 0;
+        self.track_focus();
+    }
 
-            println!(
-                "{} {:#?}",
-                color_bold(GREEN, "Runtime configuration:"),
-                self.userspace_configuration
-            );
+    /// in sidebar mode, highlight the focused tab and pane
+    /// when the focus moved since the last update, so that
+    /// a selection made in the sidebar is kept otherwise
+    fn follow_focus(&mut self) {
+        let focus = self.tab_infos.iter().find(|t| t.active).map(|tab| {
+            let pane = self.focused_pane(tab.position).map(|(_, p)| p.id);
+            (tab.position, pane)
+        });
+        if focus != self.focus {
+            self.focus = focus;
+            self.track_focus();
+        }
+    }
+
+    /// highlight the currently focused tab and pane,
+    /// unless a query is being typed
+    fn track_focus(&mut self) {
+        if !self.input.is_empty() {
+            return;
+        }
+
+        if let Some(i) = self.tab_infos.iter().position(|t| t.active) {
+            self.tab_match = Some(i);
+            if let View::Tab = self.current_view {
+                self.result_index = i;
+            }
+        }
+
+        self.track_focused_pane();
+    }
+
+    /// in Pane view, highlight the focused pane of the selected tab
+    fn track_focused_pane(&mut self) {
+        if let View::Pane = self.current_view {
+            let focused = self
+                .tab_match
+                .and_then(|p| self.focused_pane(p))
+                .map(|(i, pane)| (i, pane.id, pane.title.to_owned()));
+            if let Some((i, id, title)) = focused {
+                self.pane_match = Some(id);
+                self.pane_title_match = title;
+                self.result_index = i;
+            }
+        }
+    }
+
+    /// focused terminal pane of a tab and its index,
+    /// the focused floating pane when floating panes are visible
+    fn focused_pane(&self, tab_pos: usize) -> Option<(usize, &PaneInfo)> {
+        let floating = self
+            .tab_infos
+            .get(tab_pos)
+            .is_some_and(|t| t.are_floating_panes_visible);
+        self.pane_manifest
+            .panes
+            .get(&tab_pos)?
+            .iter()
+            .enumerate()
+            .filter(|(_, pane)| pane.is_focused && !pane.is_plugin && !pane.is_suppressed)
+            .max_by_key(|(_, pane)| pane.is_floating == floating)
+    }
+
+    fn change_mode(&mut self) {
+        let views = self.views();
+        let next = views
+            .iter()
+            .position(|v| *v == self.current_view)
+            .map(|i| views[(i + 1) % views.len()])
+            .unwrap_or(View::Tab);
+        self.switch_view(next);
+    }
+
+    /// change the view, keeping the query and selection of the
+    /// view left to restore them when coming back to it.
+    /// With `carry_query`, the query follows to the new view instead
+    fn switch_view(&mut self, view: View) {
+        self.view_states.insert(
+            self.current_view,
+            ViewState {
+                input: self.input.clone(),
+                cursor: self.input_cusror_index,
+                selected: self.selected(),
+            },
+        );
+        let carried = self
+            .config_flag("carry_query")
+            .then(|| (self.input.clone(), self.input_cusror_index));
+
+        self.set_view(view);
+
+        if let Some((input, cursor)) = carried {
+            if !input.is_empty() {
+                self.input = input;
+                self.input_cusror_index = cursor;
+                self.fuzzy_find();
+            }
+            return;
+        }
+
+        let Some(state) = self.view_states.get(&view).cloned() else {
+            return;
+        };
+        if !state.input.is_empty() {
+            self.input = state.input;
+            self.input_cusror_index = state.cursor;
+            self.fuzzy_find();
+        }
+        // a pane of another tab is not listed anymore
+        if let Some(i) = state.selected.and_then(|s| self.selected_index(&s)) {
+            self.select_index(i);
+        }
+    }
+
+    /// identity of the selected entry of the current view
+    fn selected(&self) -> Option<Selected> {
+        match self.current_view {
+            View::Tab => self
+                .tab_match
+                .and_then(|i| self.tab_infos.get(i))
+                .map(|t| Selected::Tab(t.name.to_owned())),
+            View::Pane => self.pane_match.map(Selected::Pane),
+            View::Session => self.session_match.clone().map(Selected::Session),
+            View::Command | View::Entry => self.command_match.map(Selected::Command),
+            View::Project => self
+                .project_match
+                .and_then(|i| self.projects.get(i))
+                .map(|p| Selected::Project(p.to_owned())),
+            View::Layout => self
+                .layout_match
+                .and_then(|i| self.layouts.get(i))
+                .map(|l| Selected::Layout(l.clone())),
+            View::Bookmark => self
+                .bookmark_match
+                .and_then(|i| self.bookmarks.keys().nth(i))
+                .map(|c| Selected::Bookmark(*c)),
+        }
+    }
+
+    /// current index of a selected entry, if it is still listed
+    fn selected_index(&self, selected: &Selected) -> Option<usize> {
+        match selected {
+            Selected::Tab(name) => self.tab_infos.iter().position(|t| t.name == *name),
+            Selected::Pane(id) => self
+                .scoped_panes()
+                .iter()
+                .position(|(_, p)| !p.is_plugin && p.id == *id),
+            Selected::Session(name) => self.sessions.iter().position(|s| s == name),
+            Selected::Command(i) => Some(*i),
+            Selected::Project(project) => self.projects.iter().position(|p| p == project),
+            Selected::Layout(layout) => self.layouts.iter().position(|l| l == layout),
+            Selected::Bookmark(letter) => self.bookmarks.keys().position(|c| c == letter),
         }
     }
-}
 
-pub const CYAN: u8 = 51;
-pub const GRAY_LIGHT: u8 = 238;
-pub const GRAY_DARK: u8 = 245;
-pub const WHITE: u8 = 15;
-pub const BLACK: u8 = 16;
-pub const RED: u8 = 124;
-pub const GREEN: u8 = 154;
-pub const ORANGE: u8 = 166;
+    /// views in mode order, the optional views
+    /// only when they are configured
+    fn views(&self) -> Vec<View> {
+        let mut views = vec![View::Tab, View::Pane, View::Session];
+        if self.read_only() {
+            // the other views only list actions
+            return views;
+        }
+        if !self.bookmarks.is_empty() {
+            views.push(View::Bookmark);
+        }
+        views.extend([View::Command, View::Layout]);
+        if !self.entries.is_empty() {
+            views.push(View::Entry);
+        }
+        if !self.project_roots().is_empty() {
+            views.push(View::Project);
+        }
+        views
+    }
 
-fn color_bold(color: u8, text: &str) -> String {
-    format!("{}", Style::new().fg(Fixed(color)).bold().paint(text))
+    fn set_view(&mut self, view: View) {
+        // reset input
+        self.input = String::default();
+        self.input_cusror_index = 0;
+        self.history_pos = None;
+
+        self.current_view = view;
+
+        // destination of a moved pane is picked in Tab view only
+        if view != View::Tab {
+            self.moving_panes.clear();
+        }
+
+        if let View::Pane = self.current_view {
+            // pane view
+            self.pane_match = None;
+            self.pane_title_match = String::default();
+            self.result_index = 0;
+
+            self.get_pane_at_index();
+            if self.is_sidebar() {
+                self.track_focused_pane();
+            }
+            return;
+        }
+
+        if let View::Command | View::Entry = self.current_view {
+            self.command_match = self.palette().first().map(|(i, _)| *i);
+            self.result_index = self.command_match.unwrap_or(0);
+            return;
+        }
+
+        if let View::Bookmark = self.current_view {
+            self.bookmark_match = (!self.bookmarks.is_empty()).then_some(0);
+            self.result_index = 0;
+            return;
+        }
+
+        if let View::Layout = self.current_view {
+            self.layout_match = (!self.layouts.is_empty()).then_some(0);
+            self.result_index = 0;
+            return;
+        }
+
+        if let View::Project = self.current_view {
+            self.scan_projects();
+            self.project_match = (!self.projects.is_empty()).then_some(0);
+            self.result_index = 0;
+            return;
+        }
+
+        // tab view
+        if let Some(i) = self.tab_match {
+            self.result_index = i;
+        }
+    }
+
+    fn fuzzy_find_tab(&mut self) {
+        let mut best_score = 0;
+
+        // reset match
+        self.tab_match = None;
+        self.result_index = 0;
+        for (i, t) in self.tab_infos.iter().enumerate() {
+            if let Some(score) = self.fz_matcher.fuzzy_match(&self.tab_text(t), &self.input) {
+                if score > best_score {
+                    best_score = score;
+                    self.tab_match = Some(i);
+                    self.result_index = i;
+                }
+            }
+        }
+
+        // a numeric query selects the tab at that position
+        if let Some(i) = self.tab_at_query_position() {
+            self.tab_match = Some(i);
+            self.result_index = i;
+        }
+
+        // if no found default to focus tab
+        if self.tab_match.is_none() {
+            self.tab_match = Some(self.focus_tab_pos);
+            self.result_index = self.focus_tab_pos;
+        }
+    }
+
+    /// whether the tab name matches the query, or its position
+    /// for a numeric query
+    fn tab_matches_query(&self, tab: &TabInfo) -> bool {
+        self.input.is_empty()
+            || self
+                .fz_matcher
+                .fuzzy_match(&self.tab_text(tab), &self.input)
+                .is_some()
+            || self.input.parse::<usize>() == Ok(tab.position + 1)
+    }
+
+    /// index of the tab at the (1 indexed) position typed in the query
+    fn tab_at_query_position(&self) -> Option<usize> {
+        let position = self.input.parse::<usize>().ok()?;
+        self.tab_infos
+            .iter()
+            .position(|t| t.position + 1 == position)
+    }
+
+    fn seek_tab(&mut self, idx: usize) {
+        self.result_index = idx;
+        for (i, t) in self.tab_infos.iter().enumerate() {
+            if self.tab_matches_query(t) && i == self.result_index {
+                self.tab_match = Some(i);
+                self.result_index = i;
+                break;
+            }
+        }
+    }
+
+    fn move_down_tab(&mut self) {
+        if let Some(i) = step_row(&self.tab_rows(), self.result_index, true) {
+            self.tab_match = Some(i);
+            self.result_index = i;
+        }
+    }
+
+    fn move_up_tab(&mut self) {
+        if let Some(i) = step_row(&self.tab_rows(), self.result_index, false) {
+            self.tab_match = Some(i);
+            self.result_index = i;
+        }
+    }
+
+    /// rows of the Tab view: the pinned tabs,
+    /// then the tabs matching the query
+    fn tab_rows(&self) -> Vec<usize> {
+        pinned_first(
+            self.tab_infos
+                .iter()
+                .enumerate()
+                .map(|(i, t)| (i, self.is_pinned(&t.name), self.tab_matches_query(t))),
+        )
+    }
+
+    fn move_down_session(&mut self) {
+        if let Some(i) = step_row(&self.session_rows(), self.result_index, true) {
+            self.session_match = self.sessions.get(i).cloned();
+            self.result_index = i;
+        }
+    }
+
+    fn move_up_session(&mut self) {
+        if let Some(i) = step_row(&self.session_rows(), self.result_index, false) {
+            self.session_match = self.sessions.get(i).cloned();
+            self.result_index = i;
+        }
+    }
+
+    /// rows of the Session view: the pinned sessions,
+    /// then the sessions matching the query
+    fn session_rows(&self) -> Vec<usize> {
+        pinned_first(self.sessions.iter().enumerate().map(|(i, session)| {
+            (
+                i,
+                self.is_pinned(session),
+                self.fz_matcher
+                    .fuzzy_match(&self.session_text(session), &self.input)
+                    .is_some(),
+            )
+        }))
+    }
+
+    /// update the selection of the current view from the input
+    fn fuzzy_find(&mut self) {
+        match self.current_view {
+            View::Tab => {
+                self.fuzzy_find_tab();
+            }
+
+            View::Pane => {
+                self.fuzzy_find_pane();
+            }
+            View::Session => {
+                self.fuzzy_find_session();
+            }
+            View::Command | View::Entry => {
+                self.fuzzy_find_command();
+            }
+            View::Project => {
+                self.fuzzy_find_project();
+            }
+            View::Layout => {
+                self.fuzzy_find_layout();
+            }
+            View::Bookmark => {
+                self.fuzzy_find_bookmark();
+            }
+        }
+    }
+
+    /// bookmark list label: letter, kind and name
+    fn bookmark_label(&self, letter: char, bookmark: &Bookmark) -> String {
+        let missing = if self.resolve_bookmark(bookmark).is_none() {
+            " (missing)"
+        } else {
+            ""
+        };
+        match bookmark {
+            Bookmark::Tab(name) => format!("{} tab {}{}", letter, name, missing),
+            Bookmark::Pane { tab, title, .. } => {
+                format!("{} pane {} ({}){}", letter, title, tab, missing)
+            }
+        }
+    }
+
+    fn fuzzy_find_bookmark(&mut self) {
+        let mut best_score = 0;
+
+        self.bookmark_match = None;
+        for (i, label) in self.matching_bookmarks() {
+            if let Some(score) = self.fz_matcher.fuzzy_match(&label, &self.input) {
+                if score > best_score || self.bookmark_match.is_none() {
+                    best_score = score;
+                    self.result_index = i;
+                    self.bookmark_match = Some(i);
+                }
+            }
+        }
+    }
+
+    /// labels of the bookmarks matching the input
+    fn matching_bookmarks(&self) -> Vec<(usize, String)> {
+        self.bookmarks
+            .iter()
+            .map(|(letter, bookmark)| self.bookmark_label(*letter, bookmark))
+            .enumerate()
+            .filter(|(_, l)| self.fz_matcher.fuzzy_match(l, &self.input).is_some())
+            .collect()
+    }
+
+    fn move_down_bookmark(&mut self) {
+        let matches = self.matching_bookmarks();
+        let next = matches
+            .iter()
+            .find(|(i, _)| *i > self.result_index)
+            .or(matches.first())
+            .map(|(i, _)| *i);
+        if let Some(i) = next {
+            self.bookmark_match = Some(i);
+            self.result_index = i;
+        }
+    }
+
+    fn move_up_bookmark(&mut self) {
+        let matches = self.matching_bookmarks();
+        let prev = matches
+            .iter()
+            .rev()
+            .find(|(i, _)| *i < self.result_index)
+            .or(matches.last())
+            .map(|(i, _)| *i);
+        if let Some(i) = prev {
+            self.bookmark_match = Some(i);
+            self.result_index = i;
+        }
+    }
+
+    fn fuzzy_find_layout(&mut self) {
+        let mut best_score = 0;
+
+        self.layout_match = None;
+        for (i, layout) in self.layouts.iter().enumerate() {
+            if let Some(score) = self
+                .fz_matcher
+                .fuzzy_match(&layout_label(layout), &self.input)
+            {
+                if score > best_score || self.layout_match.is_none() {
+                    best_score = score;
+                    self.result_index = i;
+                    self.layout_match = Some(i);
+                }
+            }
+        }
+    }
+
+    /// labels of the layouts matching the input
+    fn matching_layouts(&self) -> Vec<(usize, String)> {
+        self.layouts
+            .iter()
+            .map(layout_label)
+            .enumerate()
+            .filter(|(_, l)| self.fz_matcher.fuzzy_match(l, &self.input).is_some())
+            .collect()
+    }
+
+    fn move_down_layout(&mut self) {
+        let matches = self.matching_layouts();
+        let next = matches
+            .iter()
+            .find(|(i, _)| *i > self.result_index)
+            .or(matches.first())
+            .map(|(i, _)| *i);
+        if let Some(i) = next {
+            self.layout_match = Some(i);
+            self.result_index = i;
+        }
+    }
+
+    fn move_up_layout(&mut self) {
+        let matches = self.matching_layouts();
+        let prev = matches
+            .iter()
+            .rev()
+            .find(|(i, _)| *i < self.result_index)
+            .or(matches.last())
+            .map(|(i, _)| *i);
+        if let Some(i) = prev {
+            self.layout_match = Some(i);
+            self.result_index = i;
+        }
+    }
+
+    fn fuzzy_find_project(&mut self) {
+        let mut best_score = 0;
+
+        self.project_match = None;
+        for (i, project) in self.projects.iter().enumerate() {
+            if let Some(score) = self.fz_matcher.fuzzy_match(project, &self.input) {
+                if score > best_score || self.project_match.is_none() {
+                    best_score = score;
+                    self.result_index = i;
+                    self.project_match = Some(i);
+                }
+            }
+        }
+    }
+
+    /// indexes of the projects matching the input
+    fn matching_projects(&self) -> Vec<(usize, &String)> {
+        self.projects
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| self.fz_matcher.fuzzy_match(p, &self.input).is_some())
+            .collect()
+    }
+
+    fn move_down_project(&mut self) {
+        let matches = self.matching_projects();
+        let next = matches
+            .iter()
+            .find(|(i, _)| *i > self.result_index)
+            .or(matches.first())
+            .map(|(i, _)| *i);
+        if let Some(i) = next {
+            self.project_match = Some(i);
+            self.result_index = i;
+        }
+    }
+
+    fn move_up_project(&mut self) {
+        let matches = self.matching_projects();
+        let prev = matches
+            .iter()
+            .rev()
+            .find(|(i, _)| *i < self.result_index)
+            .or(matches.last())
+            .map(|(i, _)| *i);
+        if let Some(i) = prev {
+            self.project_match = Some(i);
+            self.result_index = i;
+        }
+    }
+
+    /// root directories configured with `project_roots`
+    fn project_roots(&self) -> Vec<&str> {
+        self.userspace_configuration
+            .get("project_roots")
+            .map(|roots| {
+                roots
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|r| !r.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// look for project directories on the host, the
+    /// result comes back as a `RunCommandResult` event
+    /// look up the home directory on the host, the plugin
+    /// does not see the user's environment. Only needed
+    /// to open the `~/` paths of custom entries
+    fn fetch_home(&self) {
+        let needed = self.entries.iter().any(|e| match &e.action {
+            EntryAction::Open(path) => path.starts_with('~'),
+            EntryAction::Run(_) => false,
+        });
+        if needed {
+            let mut context = BTreeMap::new();
+            context.insert(CONTEXT_KEY.to_string(), HOME_DIR.to_string());
+            run_command(&["sh", "-c", "printf %s \"$HOME\""], context);
+        }
+    }
+
+    fn scan_projects(&mut self) {
+        let roots: Vec<String> = self.project_roots().into_iter().map(shell_path).collect();
+        if roots.is_empty() || self.scanning_projects {
+            return;
+        }
+        self.scanning_projects = true;
+
+        let depth = self
+            .userspace_configuration
+            .get("project_depth")
+            .and_then(|d| d.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_PROJECT_DEPTH);
+        let markers: Vec<String> = self
+            .userspace_configuration
+            .get("project_markers")
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_PROJECT_MARKERS)
+            .split(',')
+            .map(|m| format!("-name {}", shell_quote(m.trim())))
+            .collect();
+
+        // a project directory contains one of the markers,
+        // exits with the status of find
+        let script = format!(
+            "found=$(find {} -mindepth 1 -maxdepth {} \\( {} \\)); status=$?; \
+             printf '%s\\n' \"$found\" | sed 's|/[^/]*$||' | sort -u; exit $status",
+            roots.join(" "),
+            depth + 1,
+            markers.join(" -o ")
+        );
+        let mut context = BTreeMap::new();
+        context.insert(CONTEXT_KEY.to_string(), SCAN_PROJECTS.to_string());
+        run_command(&["sh", "-c", &script], context);
+    }
+
+    fn fuzzy_find_command(&mut self) {
+        let mut best_score = 0;
+
+        let mut best = None;
+        for (i, name) in self.palette() {
+            if let Some(score) = self.fz_matcher.fuzzy_match(name, &self.input) {
+                // an empty query matches everything with a zero score
+                if score > best_score || best.is_none() {
+                    best_score = score;
+                    best = Some(i);
+                }
+            }
+        }
+        self.command_match = best;
+        if let Some(i) = best {
+            self.result_index = i;
+        }
+    }
+
+    /// (index, name) of the actions listed in the current view,
+    /// indexes count the built-in commands then the custom entries
+    fn palette(&self) -> Vec<(usize, &str)> {
+        let entries = self.entries.iter().map(|e| e.name.as_str());
+        match self.current_view {
+            View::Entry => entries
+                .enumerate()
+                .map(|(i, name)| (COMMANDS.len() + i, name))
+                .collect(),
+            _ => {
+                let sidebar = self.is_sidebar();
+                COMMANDS
+                    .iter()
+                    .map(|c| (c.name, !(sidebar && c.focused_pane)))
+                    .chain(entries.map(|name| (name, true)))
+                    .enumerate()
+                    .filter(|(_, (_, listed))| *listed)
+                    .map(|(i, (name, _))| (i, name))
+                    .collect()
+            }
+        }
+    }
+
+    /// palette actions matching the input
+    fn matching_commands(&self) -> Vec<(usize, &str)> {
+        self.palette()
+            .into_iter()
+            .filter(|(_, name)| self.fz_matcher.fuzzy_match(name, &self.input).is_some())
+            .collect()
+    }
+
+    fn move_down_command(&mut self) {
+        let matches = self.matching_commands();
+        let next = matches
+            .iter()
+            .find(|(i, _)| *i > self.result_index)
+            .or(matches.first())
+            .map(|(i, _)| *i);
+        if let Some(i) = next {
+            self.command_match = Some(i);
+            self.result_index = i;
+        }
+    }
+
+    fn move_up_command(&mut self) {
+        let matches = self.matching_commands();
+        let prev = matches
+            .iter()
+            .rev()
+            .find(|(i, _)| *i < self.result_index)
+            .or(matches.last())
+            .map(|(i, _)| *i);
+        if let Some(i) = prev {
+            self.command_match = Some(i);
+            self.result_index = i;
+        }
+    }
+
+    fn fuzzy_find_session(&mut self) {
+        let mut best_score = 0;
+
+        self.session_match = None;
+        for (i, session) in self.sessions.iter().enumerate() {
+            if let Some(score) = self
+                .fz_matcher
+                .fuzzy_match(&self.session_text(session), &self.input)
+            {
+                if score > best_score {
+                    best_score = score;
+                    self.result_index = i;
+
+                    self.session_match = Some(session.to_owned());
+                }
+            }
+        }
+    }
+
+    fn fuzzy_find_pane(&mut self) {
+        let mut best_score = 0;
+        let mut best = None;
+
+        // reset match
+        self.pane_match = None;
+        self.pane_title_match = String::default();
+        for (i, (_, pane)) in self.scoped_panes().into_iter().enumerate() {
+            if pane.is_plugin {
+                continue;
+            }
+            if let Some(score) = self
+                .fz_matcher
+                .fuzzy_match(&self.pane_text(pane), &self.input)
+            {
+                if score > best_score {
+                    best_score = score;
+                    best = Some((i, pane.id, pane.title.to_owned()));
+                }
+            }
+        }
+        if let Some((i, id, title)) = best {
+            self.pane_match = Some(id);
+            self.pane_title_match = title;
+            self.result_index = i;
+        }
+    }
+
+    fn get_pane_at_index(&mut self) {
+        if !self.pane_rows().contains(&self.result_index) {
+            return;
+        }
+        let pane = self
+            .scoped_panes()
+            .get(self.result_index)
+            .map(|(_, pane)| (pane.id, pane.title.to_owned()));
+        if let Some((id, title)) = pane {
+            self.pane_match = Some(id);
+            self.pane_title_match = title;
+        }
+    }
+
+    /// panes listed in the Pane view with their tab position:
+    /// the panes of the selected tab, or the panes of all
+    /// tabs for a #tag query
+    fn scoped_panes(&self) -> Vec<(usize, &PaneInfo)> {
+        if self.input.starts_with(TAG_PREFIX) {
+            let mut positions: Vec<&usize> = self.pane_manifest.panes.keys().collect();
+            positions.sort();
+            return positions
+                .into_iter()
+                .flat_map(|p| {
+                    self.pane_manifest.panes[p]
+                        .iter()
+                        .map(move |pane| (*p, pane))
+                })
+                .collect();
+        }
+        self.tab_match
+            .and_then(|p| Some((p, self.pane_manifest.panes.get(&p)?)))
+            .map(|(p, panes)| panes.iter().map(|pane| (p, pane)).collect())
+            .unwrap_or_default()
+    }
+
+    /// tab position of the selected pane, which is not
+    /// the selected tab for a #tag query
+    fn selected_pane_tab(&self) -> Option<usize> {
+        self.scoped_panes()
+            .iter()
+            .find(|(_, pane)| Some(pane.id) == self.pane_match)
+            .map(|(p, _)| *p)
+            .or(self.tab_match)
+    }
+
+    fn move_down_pane(&mut self) {
+        self.step_pane(true);
+    }
+
+    fn move_up_pane(&mut self) {
+        self.step_pane(false);
+    }
+
+    fn step_pane(&mut self, forward: bool) {
+        self.pane_match = None;
+        self.pane_title_match = String::default();
+        let next = step_row(&self.pane_rows(), self.result_index, forward);
+        let pane = next.and_then(|i| {
+            let (_, pane) = self.scoped_panes().get(i).copied()?;
+            Some((i, pane.id, pane.title.to_owned()))
+        });
+        if let Some((i, id, title)) = pane {
+            self.pane_match = Some(id);
+            self.pane_title_match = title;
+            self.result_index = i;
+        }
+    }
+
+    /// rows of the Pane view: the pinned panes,
+    /// then the panes matching the query
+    fn pane_rows(&self) -> Vec<usize> {
+        pinned_first(
+            self.scoped_panes()
+                .into_iter()
+                .enumerate()
+                .filter(|(_, (_, pane))| !pane.is_plugin)
+                .map(|(i, (_, pane))| {
+                    (
+                        i,
+                        self.is_pinned(&pane.title),
+                        self.fz_matcher
+                            .fuzzy_match(&self.pane_text(pane), &self.input)
+                            .is_some(),
+                    )
+                }),
+        )
+    }
+
+    /// name followed by the aliases and tags
+    fn annotated_text(&self, name: &str, target: Annotated) -> String {
+        match self.annotations.get(&target) {
+            Some(text) => format!("{} {}", name, text),
+            None => name.to_string(),
+        }
+    }
+
+    fn tab_text(&self, tab: &TabInfo) -> String {
+        self.annotated_text(&tab.name, Annotated::Tab(tab.name.to_owned()))
+    }
+
+    fn pane_text(&self, pane: &PaneInfo) -> String {
+        self.annotated_text(&pane.title, Annotated::Pane(pane.id))
+    }
+
+    fn session_text(&self, session: &str) -> String {
+        self.annotated_text(session, Annotated::Session(session.to_string()))
+    }
+
+    /// edit the aliases and tags of the selected entry
+    fn start_annotate(&mut self) {
+        let target = match self.current_view {
+            View::Tab => self
+                .tab_match
+                .and_then(|m| self.tab_infos.get(m))
+                .map(|t| Annotated::Tab(t.name.to_owned())),
+            View::Pane => self.pane_match.map(Annotated::Pane),
+            View::Session => self.session_match.clone().map(Annotated::Session),
+            _ => None,
+        };
+        if let Some(target) = target {
+            let text = self.annotations.get(&target).cloned().unwrap_or_default();
+            self.overlay = Some(Overlay::Annotate(target, text));
+        }
+    }
+
+    fn set_annotation(&mut self, target: Annotated, text: String) {
+        let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if text.is_empty() {
+            self.annotations.remove(&target);
+        } else {
+            self.annotations.insert(target, text);
+        }
+        self.save_annotations();
+    }
+
+    /// annotations file of the current session in the plugin data dir
+    fn annotations_path(&self) -> Option<PathBuf> {
+        let session = self.current_session.as_ref()?;
+        Some(PathBuf::from(DATA_DIR).join(format!("annotations-{}", session)))
+    }
+
+    fn save_annotations(&self) {
+        let Some(path) = self.annotations_path() else {
+            return;
+        };
+        let lines: Vec<String> = self
+            .annotations
+            .iter()
+            .filter_map(|(target, text)| match target {
+                Annotated::Tab(name) => Some(format!(
+                    "tab\t{}\t{}",
+                    escape_field(name),
+                    escape_field(text)
+                )),
+                // pane ids restart with the session, so
+                // the tab name and title are saved along
+                Annotated::Pane(id) => {
+                    let (position, pane) = self.find_pane(*id)?;
+                    let tab = self.tab_infos.iter().find(|t| t.position == position)?;
+                    Some(format!(
+                        "pane\t{}\t{}\t{}\t{}",
+                        id,
+                        escape_field(&tab.name),
+                        escape_field(&pane.title),
+                        escape_field(text)
+                    ))
+                }
+                Annotated::Session(name) => Some(format!(
+                    "session\t{}\t{}",
+                    escape_field(name),
+                    escape_field(text)
+                )),
+            })
+            // not attached yet
+            .chain(self.pending_annotations.iter().filter_map(|(pane, text)| {
+                let Bookmark::Pane { id, tab, title } = pane else {
+                    return None;
+                };
+                Some(format!(
+                    "pane\t{}\t{}\t{}\t{}",
+                    id,
+                    escape_field(tab),
+                    escape_field(title),
+                    escape_field(text)
+                ))
+            }))
+            .collect();
+        let _ = std::fs::write(path, lines.join("\n"));
+    }
+
+    fn load_annotations(&mut self) {
+        let Some(content) = self
+            .annotations_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+        else {
+            return;
+        };
+        for line in content.lines() {
+            let target = match line.split('\t').collect::<Vec<&str>>().as_slice() {
+                ["tab", name, text] => Some((Annotated::Tab(unescape_field(name)), *text)),
+                ["pane", id, tab, title, text] => {
+                    if let Ok(id) = id.parse() {
+                        let pane = Bookmark::Pane {
+                            id,
+                            tab: unescape_field(tab),
+                            title: unescape_field(title),
+                        };
+                        self.pending_annotations.push((pane, unescape_field(text)));
+                    }
+                    None
+                }
+                ["session", name, text] => Some((Annotated::Session(unescape_field(name)), *text)),
+                _ => None,
+            };
+            if let Some((target, text)) = target {
+                self.annotations
+                    .entry(target)
+                    .or_insert_with(|| unescape_field(text));
+            }
+        }
+        self.attach_annotations();
+    }
+
+    /// attach the loaded pane annotations to the panes
+    /// they were saved for, once the tabs and panes are known
+    fn attach_annotations(&mut self) {
+        if self.pending_annotations.is_empty()
+            || self.tab_infos.is_empty()
+            || self.pane_manifest.panes.is_empty()
+        {
+            return;
+        }
+        for (pane, text) in std::mem::take(&mut self.pending_annotations) {
+            if let Some((_, Some(id))) = self.resolve_saved_bookmark(&pane) {
+                self.annotations.entry(Annotated::Pane(id)).or_insert(text);
+            }
+        }
+    }
+
+    /// forget the annotations of the closed panes
+    fn prune_annotations(&mut self) {
+        if self.pane_manifest.panes.is_empty() {
+            return;
+        }
+        let before = self.annotations.len();
+        let panes: Vec<u32> = self
+            .pane_manifest
+            .panes
+            .values()
+            .flatten()
+            .filter(|pane| !pane.is_plugin)
+            .map(|pane| pane.id)
+            .collect();
+        self.annotations.retain(|target, _| match target {
+            Annotated::Pane(id) => panes.contains(id),
+            _ => true,
+        });
+        if self.annotations.len() != before {
+            self.save_annotations();
+        }
+    }
+
+    /// tab position and info of a terminal pane
+    fn find_pane(&self, id: u32) -> Option<(usize, &PaneInfo)> {
+        self.pane_manifest.panes.iter().find_map(|(p, panes)| {
+            panes
+                .iter()
+                .find(|pane| pane.id == id && !pane.is_plugin)
+                .map(|pane| (*p, pane))
+        })
+    }
+
+    fn is_pinned(&self, name: &str) -> bool {
+        self.pins.iter().any(|pattern| glob_match(pattern, name))
+    }
+
+    /// pin the selected tab, pane or session by name,
+    /// or unpin it
+    fn toggle_pin(&mut self) {
+        let name = match self.current_view {
+            View::Tab => self
+                .tab_match
+                .and_then(|m| self.tab_infos.get(m))
+                .map(|t| t.name.to_owned()),
+            View::Pane => self.pane_match.map(|_| self.pane_title_match.to_owned()),
+            View::Session => self.session_match.clone(),
+            _ => None,
+        };
+        let Some(name) = name else {
+            return;
+        };
+
+        if self.is_pinned(&name) {
+            self.pins.retain(|pattern| !glob_match(pattern, &name));
+        } else {
+            self.pins.push(name);
+        }
+        self.save_pins();
+    }
+
+    /// name patterns of the `pinned` option
+    fn configured_pins(&self) -> Vec<String> {
+        self.userspace_configuration
+            .get("pinned")
+            .map(|pinned| {
+                pinned
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// save the pins toggled with Alt+p as changes to the
+    /// `pinned` option, so that editing the option still applies
+    fn save_pins(&self) {
+        let configured = self.configured_pins();
+        let pinned = self
+            .pins
+            .iter()
+            .filter(|p| !configured.contains(p))
+            .map(|p| format!("pin\t{}", escape_field(p)));
+        let unpinned = configured
+            .iter()
+            .filter(|p| !self.pins.contains(p))
+            .map(|p| format!("unpin\t{}", escape_field(p)));
+        let lines: Vec<String> = pinned.chain(unpinned).collect();
+        let _ = std::fs::write(PathBuf::from(DATA_DIR).join("pins"), lines.join("\n"));
+    }
+
+    fn load_pins(&mut self) {
+        self.pins = self.configured_pins();
+        let Ok(content) = std::fs::read_to_string(PathBuf::from(DATA_DIR).join("pins")) else {
+            return;
+        };
+        for line in content.lines() {
+            match line.split_once('\t') {
+                Some(("pin", pattern)) => {
+                    let pattern = unescape_field(pattern);
+                    if !self.pins.contains(&pattern) {
+                        self.pins.push(pattern);
+                    }
+                }
+                Some(("unpin", pattern)) => {
+                    let pattern = unescape_field(pattern);
+                    self.pins.retain(|p| *p != pattern);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// remove_input_at_index  removes char at the
+    /// cursor index and update input.
+    /// Returns true if the input has change
+    fn remove_input_at_index(&mut self) -> bool {
+        if self.input.is_empty() {
+            self.input.pop();
+        } else if self.input_cusror_index > 0 && self.input_cusror_index <= self.input.len() {
+            self.input.remove(self.input_cusror_index - 1);
+            // update cursor index
+            self.input_cusror_index -= 1;
+
+            return true;
+        } else if self.input_cusror_index == 0 {
+            self.input.remove(0);
+        }
+        false
+    }
+
+    /// remove_input_at_index  removes char at the
+    /// cursor index and update input.
+    /// Returns true if the input has change
+    fn insert_input_at_index(&mut self, c: char) -> bool {
+        if self.input.is_empty() {
+            self.input.push(c);
+
+            // update cursor index
+            self.input_cusror_index += 1;
+            return true;
+        } else if self.input_cusror_index > 0 && self.input_cusror_index <= self.input.len() {
+            self.input.insert(self.input_cusror_index, c);
+            // update cursor index
+            self.input_cusror_index += 1;
+
+            return true;
+        } else if self.input_cusror_index == 0 {
+            self.input.insert(0, c);
+            self.input_cusror_index += 1;
+        }
+        false
+    }
+
+    /// number of terminal panes in the tab at position
+    fn tab_pane_count(&self, position: usize) -> usize {
+        self.pane_manifest
+            .panes
+            .get(&position)
+            .map(|panes| panes.iter().filter(|p| !p.is_plugin).count())
+            .unwrap_or(0)
+    }
+
+    /// compact badges describing the state of a tab
+    /// (pane count, fullscreen, sync, floating, hidden panes,
+    /// swap layout and other clients focused on it)
+    fn tab_badges(&self, tab: &TabInfo) -> Vec<(String, Badge)> {
+        let mut badges = vec![(
+            format!("{}p", self.tab_pane_count(tab.position)),
+            Badge::Muted,
+        )];
+
+        if tab.is_fullscreen_active {
+            badges.push(("[full]".to_string(), Badge::Alert));
+        }
+        if tab.is_sync_panes_active {
+            badges.push(("[sync]".to_string(), Badge::Alert));
+        }
+        if tab.are_floating_panes_visible {
+            badges.push(("[float]".to_string(), Badge::Info));
+        }
+        if tab.panes_to_hide > 0 {
+            badges.push((format!("[+{} hidden]", tab.panes_to_hide), Badge::Muted));
+        }
+        if let Some(layout) = &tab.active_swap_layout_name {
+            // `*` once the panes were moved away from the swap layout
+            let dirty = if tab.is_swap_layout_dirty { "*" } else { "" };
+            badges.push((format!("[{}{}]", layout, dirty), Badge::Info));
+        }
+        if !tab.other_focused_clients.is_empty() {
+            let clients: Vec<String> = tab
+                .other_focused_clients
+                .iter()
+                .map(|c| c.to_string())
+                .collect();
+            badges.push((format!("[clients {}]", clients.join(",")), Badge::Highlight));
+        }
+
+        badges
+    }
+
+    /// tab badges painted with the theme colors
+    fn tab_indicators(&self, tab: &TabInfo) -> String {
+        self.tab_badges(tab)
+            .iter()
+            .map(|(text, badge)| {
+                let style = match badge {
+                    Badge::Muted => self.theme.muted,
+                    Badge::Info => self.theme.badge,
+                    Badge::Alert => self.theme.alert,
+                    Badge::Highlight => self.theme.highlight,
+                };
+                style.paint(text.as_str()).to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// build a result list item prefixed by its row index,
+    /// highlighting the characters matching the input
+    fn list_item(
+        &self,
+        text: &str,
+        selected: bool,
+        width: usize,
+        index: &str,
+        marked: bool,
+    ) -> NestedListItem {
+        let prefix = self.row_prefix(index, marked);
+        let offset = prefix.chars().count();
+        let truncated = truncate(text, width.saturating_sub(prefix.width()));
+        let mut item = NestedListItem::new(format!("{}{}", prefix, truncated));
+        if marked {
+            item = item.color_range(3, ..offset);
+        }
+        if !self.input.is_empty() {
+            if let Some((_, indices)) = self.fz_matcher.fuzzy_indices(text, &self.input) {
+                let indices = visible_indices(indices, text, &truncated);
+                item = item.color_indices(0, indices.into_iter().map(|i| i + offset).collect());
+            }
+        }
+        if selected {
+            item = item.selected();
+        }
+        item
+    }
+
+    /// row index followed by a mark column
+    /// when entries are marked in the current view
+    fn row_prefix(&self, index: &str, marked: bool) -> String {
+        if marked {
+            format!("{}* ", index)
+        } else if self.marked_count() > 0 {
+            format!("{}  ", index)
+        } else {
+            format!("{} ", index)
+        }
+    }
+
+    /// build a pane list item prefixed by its hint label,
+    /// the typed part of the label is highlighted
+    fn hint_list_item(&self, label: &str, title: &str, width: usize) -> NestedListItem {
+        let prefix = format!("[{}]", label);
+        let text = format!(
+            "{} {}",
+            prefix,
+            truncate(title, width.saturating_sub(prefix.width() + 1))
+        );
+        let label_len = prefix.chars().count();
+        let mut item = NestedListItem::new(&text);
+        if label.starts_with(&self.hint_input) && !self.hint_input.is_empty() {
+            let typed = self.hint_input.chars().count();
+            item = item
+                .color_range(0, 1..1 + typed)
+                .color_range(1, 1 + typed..label_len - 1);
+        } else {
+            item = item.color_range(1, 1..label_len - 1);
+        }
+        item
+    }
+
+    /// build a tab list item followed by its badges,
+    /// badges that do not fit in width are dropped
+    fn tab_list_item(
+        &self,
+        tab: &TabInfo,
+        selected: bool,
+        width: usize,
+        index: &str,
+        marked: bool,
+    ) -> NestedListItem {
+        let prefix = self.row_prefix(index, marked);
+        let offset = prefix.chars().count();
+        let name = self.tab_text(tab);
+        let truncated = truncate(&name, width.saturating_sub(prefix.width()));
+        let mut text = format!("{}{}", prefix, truncated);
+        let mut ranges = vec![];
+        for (badge_text, badge) in self.tab_badges(tab) {
+            if text.width() + 1 + badge_text.width() > width {
+                break;
+            }
+            text.push(' ');
+            let start = text.chars().count();
+            text.push_str(&badge_text);
+            if let Some(level) = badge.color_index() {
+                ranges.push((level, start..text.chars().count()));
+            }
+        }
+
+        let mut item = NestedListItem::new(&text);
+        if marked {
+            item = item.color_range(3, ..offset);
+        }
+        if !self.input.is_empty() {
+            if let Some((_, indices)) = self.fz_matcher.fuzzy_indices(&name, &self.input) {
+                let indices = visible_indices(indices, &name, &truncated);
+                item = item.color_indices(0, indices.into_iter().map(|i| i + offset).collect());
+            }
+        }
+        for (level, range) in ranges {
+            item = item.color_range(level, range);
+        }
+        if selected {
+            item = item.selected();
+        }
+        item
+    }
+
+    /// build the visible result list items, scrolled
+    /// so that the selected entry is always displayed.
+    /// Also returns the entry index of each row
+    fn list_items<T>(
+        &self,
+        matches: &[(usize, T)],
+        capacity: usize,
+        build: impl Fn(&T, bool, &str) -> NestedListItem,
+    ) -> (Vec<NestedListItem>, Vec<usize>) {
+        let selected = matches
+            .iter()
+            .position(|(i, _)| *i == self.result_index)
+            .unwrap_or(0);
+
+        let (window, overflow) = list_window(matches.len(), selected, capacity);
+        let entries: Vec<usize> = matches[window.clone()].iter().map(|(i, _)| *i).collect();
+        let mut items: Vec<NestedListItem> = matches[window]
+            .iter()
+            .enumerate()
+            .map(|(row, (i, entry))| build(entry, *i == self.result_index, &row_label(row)))
+            .collect();
+        if overflow {
+            items.push(NestedListItem::new("..."));
+        }
+        (items, entries)
+    }
+
+    /// print result list at line y and move the cursor
+    /// after the list
+    fn print_list(&self, items: Vec<NestedListItem>, y: usize, cols: usize) {
+        let len = items.len();
+        print_nested_list_with_coordinates(items, 0, y, Some(cols), None);
+        for _ in 0..len {
+            println!();
+        }
+    }
+
+    /// print the view ribbons, using shorter labels
+    /// when the pane is too narrow.
+    /// Returns the columns taken by each ribbon
+    fn print_ribbons(&self, cols: usize) -> Vec<(std::ops::Range<usize>, View)> {
+        let labels: Vec<([&str; 3], View)> =
+            self.views().into_iter().map(|v| (v.labels(), v)).collect();
+
+        // pick the longest labels that fit
+        let variant = (0..3)
+            .find(|v| {
+                labels
+                    .iter()
+                    .map(|(l, _)| l[*v].width() + RIBBON_PADDING + 1)
+                    .sum::<usize>()
+                    < cols
+            })
+            .unwrap_or(2);
+
+        let mut hitboxes = vec![];
+        let mut x = 1;
+        for (label, view) in labels {
+            let text = if view == self.current_view {
+                Text::new(label[variant]).selected()
+            } else {
+                Text::new(label[variant])
+            };
+            print_ribbon_with_coordinates(text, x, 0, None, None);
+            let width = label[variant].width() + RIBBON_PADDING;
+            hitboxes.push((x..x + width, view));
+            x += width + 1;
+        }
+        hitboxes
+    }
+
+    /// print a summary line "label -> value",
+    /// truncated to the pane width
+    fn print_summary(&self, label: &str, value: Option<&str>, extra: Option<String>, cols: usize) {
+        let available = cols.saturating_sub(label.width() + 1);
+        match value {
+            Some(value) => {
+                let value = truncate(value, available);
+                match extra {
+                    Some(extra) if value.width() + 1 + ansi_width(&extra) <= available => {
+                        println!(
+                            "{} {} {}",
+                            self.theme.label.paint(label),
+                            self.theme.selected.paint(value.as_str()),
+                            extra
+                        );
+                    }
+                    _ => {
+                        println!(
+                            "{} {}",
+                            self.theme.label.paint(label),
+                            self.theme.selected.paint(value.as_str())
+                        );
+                    }
+                }
+            }
+            None => {
+                println!(
+                    "{} {}",
+                    self.theme.label.paint(label),
+                    self.theme
+                        .muted
+                        .paint(truncate("No matches found", available).as_str())
+                );
+            }
+        }
+    }
+
+    /// print the input prompt
+    fn print_prompt(&self, _rows: usize, cols: usize) {
+        // if not enough space in UI
+        // input prompt
+        match &self.overlay {
+            Some(Overlay::Confirm(action)) => {
+                println!(
+                    "{} {}",
+                    self.theme
+                        .alert
+                        .paint(truncate(&action.description(), cols.saturating_sub(7)).as_str()),
+                    self.theme.label.paint("[y/n]"),
+                );
+                return;
+            }
+            Some(Overlay::Annotate(_, text)) => {
+                println!(
+                    "{} {}{}",
+                    self.theme.prompt.paint(" aliases/#tags > "),
+                    self.theme
+                        .input
+                        .paint(truncate_start(text, cols.saturating_sub(20)).as_str()),
+                    self.theme.cursor.paint("┃"),
+                );
+                return;
+            }
+            Some(Overlay::Bookmark) => {
+                println!(
+                    "{} {}",
+                    self.theme.prompt.paint(" bookmark as > "),
+                    self.theme.cursor.paint("┃"),
+                );
+                return;
+            }
+            Some(Overlay::Jump) => {
+                println!(
+                    "{} {}",
+                    self.theme.prompt.paint(" jump to > "),
+                    self.theme.cursor.paint("┃"),
+                );
+                return;
+            }
+            Some(Overlay::Rename(_, name)) => {
+                println!(
+                    "{} {}{}",
+                    self.theme.prompt.paint(" rename > "),
+                    self.theme
+                        .input
+                        .paint(truncate_start(name, cols.saturating_sub(12)).as_str()),
+                    self.theme.cursor.paint("┃"),
+                );
+                return;
+            }
+            None => (),
+        }
+
+        if self.hint_mode {
+            println!(
+                "{} {}{}",
+                self.theme.prompt.paint(" hint > "),
+                self.theme.input.paint(self.hint_input.as_str()),
+                self.theme.cursor.paint("┃"),
+            );
+            return;
+        }
+
+        let prompt = self.theme.prompt.paint(" > ").to_string();
+        if self.input.is_empty() {
+            println!(
+                "{} {}{}",
+                prompt,
+                self.theme.cursor.paint("┃"),
+                self.theme
+                    .placeholder
+                    .paint(truncate("search pattern", cols.saturating_sub(5)).as_str()),
+            );
+        } else {
+            self.print_non_empty_input_prompt(prompt, cols);
+        }
+    }
+
+    fn print_non_empty_input_prompt(&self, prompt: String, cols: usize) {
+        // prompt, space and cursor
+        let available = cols.saturating_sub(5);
+        match self.input_cusror_index.cmp(&self.input.len()) {
+            std::cmp::Ordering::Equal => {
+                println!(
+                    "{} {}{}",
+                    prompt,
+                    self.theme
+                        .input
+                        .paint(truncate_start(&self.input, available).as_str()),
+                    self.theme.cursor.paint("┃"),
+                );
+            }
+            std::cmp::Ordering::Less => {
+                let copy = self.input.clone();
+                let (before_curs, after_curs) = copy.split_at(self.input_cusror_index);
+                let before_curs = truncate_start(before_curs, available);
+                let after_curs =
+                    truncate(after_curs, available.saturating_sub(before_curs.width()));
+
+                println!(
+                    "{} {}{}{}",
+                    prompt,
+                    self.theme.input.paint(before_curs.as_str()),
+                    self.theme.cursor.paint("┃"),
+                    self.theme.input.paint(after_curs.as_str())
+                );
+            }
+
+            std::cmp::Ordering::Greater => (),
+        }
+    }
+
+    /// number of summary lines printed under the list
+    fn summary_lines(&self) -> usize {
+        if self.shell_command().is_some() {
+            return 2;
+        }
+        match self.current_view {
+            View::Pane => 2,
+            View::Tab if !self.moving_panes.is_empty() => 2,
+            View::Tab
+            | View::Session
+            | View::Command
+            | View::Entry
+            | View::Project
+            | View::Layout
+            | View::Bookmark => 1,
+        }
+    }
+}
+
+register_plugin!(State);
+impl ZellijPlugin for State {
+    fn load(&mut self, configuration: BTreeMap<String, String>) {
+        self.userspace_configuration = configuration;
+        self.theme = Theme::new(
+            &zellij_utils::shared::default_palette(),
+            &self.userspace_configuration,
+        );
+
+        // the action permissions are requested once reading is granted,
+        // so that Pathfinder can run read-only when they are denied
+        self.request_read_permission();
+        subscribe(&[
+            EventType::PermissionRequestResult,
+            EventType::ModeUpdate,
+            EventType::TabUpdate,
+            EventType::PaneUpdate,
+            EventType::Key,
+            EventType::SessionUpdate,
+            EventType::Mouse,
+            EventType::Timer,
+            EventType::RunCommandResult,
+            EventType::Visible,
+        ]);
+
+        for (key, value) in &self.userspace_configuration {
+            let Some(name) = key.strip_prefix(ENTRY_PREFIX) else {
+                continue;
+            };
+            match Entry::parse(name, value) {
+                Ok(entry) => self.entries.push(entry),
+                Err(reason) => self.entry_errors.push(format!("{}: {}", name, reason)),
+            }
+        }
+
+        self.load_pins();
+        self.load_history();
+
+        self.cwd = get_plugin_ids().initial_cwd;
+
+        if self.config_flag("start_in_hint_mode") {
+            self.enter_hint_mode();
+        }
+    }
+
+    fn update(&mut self, event: Event) -> bool {
+        let mut should_render = true;
+        match event {
+            Event::PermissionRequestResult(status) => {
+                self.permissions = match (self.permissions, status) {
+                    (Permissions::RequestingRead, PermissionStatus::Granted) => {
+                        self.request_action_permissions();
+                        Permissions::RequestingActions
+                    }
+                    (Permissions::RequestingRead, PermissionStatus::Denied) => Permissions::Denied,
+                    (Permissions::RequestingActions, PermissionStatus::Granted) => {
+                        rename_plugin_pane(get_plugin_ids().plugin_id, "PathFinder");
+                        self.fetch_home();
+                        Permissions::Granted
+                    }
+                    // zellij stores the last granted set only, ask for the
+                    // read permission alone so that it is kept
+                    (Permissions::RequestingActions, PermissionStatus::Denied) => {
+                        request_permission(&[PermissionType::ReadApplicationState]);
+                        Permissions::RequestingReadOnly
+                    }
+                    (Permissions::RequestingReadOnly, PermissionStatus::Granted) => {
+                        Permissions::ReadOnly
+                    }
+                    (Permissions::RequestingReadOnly, PermissionStatus::Denied) => {
+                        Permissions::Denied
+                    }
+                    (permissions, _) => permissions,
+                };
+                if self.read_only() && !self.views().contains(&self.current_view) {
+                    self.set_view(View::Tab);
+                }
+            }
+            Event::ModeUpdate(mode_info) => {
+                self.theme = Theme::new(&mode_info.style.colors, &self.userspace_configuration);
+                should_render = true;
+            }
+            Event::TabUpdate(tab_info) => {
+                let previous = std::mem::replace(&mut self.tab_infos, tab_info);
+                self.resolve_tabs(&previous);
+                self.attach_bookmarks();
+                self.attach_annotations();
+                self.rename_layout_tab();
+                self.get_focused_tab();
+                self.resolve_panes();
+                if self.is_sidebar() {
+                    self.follow_focus();
+                }
+                should_render = true;
+            }
+            // reopened after hide_on_close, the active tab may have changed
+            Event::Visible(true) => self.track_focus(),
+            Event::PaneUpdate(pane_manifest) => {
+                self.pane_manifest = pane_manifest;
+                self.attach_bookmarks();
+                self.attach_annotations();
+                self.prune_annotations();
+                self.resolve_panes();
+                if self.is_sidebar() {
+                    self.follow_focus();
+                }
+                should_render = true;
+            }
+            Event::SessionUpdate(session_infos, _) => {
+                if let Some(current) = session_infos.iter().find(|s| s.is_current_session) {
+                    self.current_session = Some(current.name.to_owned());
+                    self.layouts = current.available_layouts.clone();
+                }
+                if !self.bookmarks_loaded && self.current_session.is_some() {
+                    self.bookmarks_loaded = true;
+                    self.load_bookmarks();
+                    self.load_annotations();
+                }
+                self.sessions = session_infos
+                    .into_iter()
+                    .map(|session_info| session_info.name)
+                    .collect();
+                self.resolve_sessions();
+                // self.sessions = session_infos;
+            }
+
+            Event::Key(key) => {
+                should_render = self.handle_key_event(key);
+            }
+            Event::Mouse(mouse) => {
+                should_render = self.handle_mouse_event(mouse);
+            }
+            Event::RunCommandResult(exit_code, stdout, stderr, context)
+                if context.get(CONTEXT_KEY).map(String::as_str) == Some(SCAN_PROJECTS) =>
+            {
+                self.scanning_projects = false;
+                self.project_error = (exit_code != Some(0)).then(|| {
+                    let stderr = String::from_utf8_lossy(&stderr);
+                    match stderr.lines().find(|l| !l.trim().is_empty()) {
+                        Some(line) => line.trim().to_string(),
+                        None => format!("exit status {:?}", exit_code),
+                    }
+                });
+                self.projects = String::from_utf8_lossy(&stdout)
+                    .lines()
+                    .filter(|l| !l.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                if self.current_view == View::Project {
+                    self.project_match = (!self.projects.is_empty()).then_some(0);
+                    self.result_index = 0;
+                    if !self.input.is_empty() {
+                        self.fuzzy_find_project();
+                    }
+                }
+            }
+            Event::RunCommandResult(Some(0), stdout, _, context)
+                if context.get(CONTEXT_KEY).map(String::as_str) == Some(HOME_DIR) =>
+            {
+                let home = String::from_utf8_lossy(&stdout).trim().to_string();
+                self.home = (!home.is_empty()).then(|| PathBuf::from(home));
+                should_render = false;
+            }
+            Event::Timer(_) => {
+                // undo is only available until the last timer expires
+                self.undo_timers = self.undo_timers.saturating_sub(1);
+                if self.undo_timers == 0 {
+                    should_render = self.undo_rename.take().is_some();
+                }
+            }
+            _ => (),
+        };
+
+        should_render
+    }
+
+    fn pipe(&mut self, pipe_message: PipeMessage) -> bool {
+        if pipe_message.name != PIPE_NAME {
+            return false;
+        }
+
+        match pipe_message.payload.as_deref() {
+            None | Some("show") => {
+                show_self(true);
+                self.track_focus();
+                true
+            }
+            Some("hint") => {
+                show_self(true);
+                self.track_focus();
+                self.enter_hint_mode();
+                true
+            }
+            Some(payload) => {
+                // "mark <letter>" bookmarks the focused pane,
+                // "jump <letter>" focuses a bookmark
+                let letter = payload
+                    .split_once(' ')
+                    .and_then(|(_, l)| l.trim().chars().next());
+                match (payload.split(' ').next(), letter) {
+                    (Some("mark"), Some(letter)) => {
+                        if let Some(bookmark) = self.focused_bookmark() {
+                            self.set_bookmark(letter, bookmark);
+                        }
+                        true
+                    }
+                    (Some("jump"), Some(letter)) => {
+                        if let Some(target) = self.bookmark_target(letter) {
+                            focus_target(target);
+                        }
+                        false
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    fn render(&mut self, rows: usize, cols: usize) {
+        // get the shell args from config
+
+        if self.permissions == Permissions::Denied {
+            println!(
+                "Pathfinder needs to read the session state to list its tabs, panes and sessions,"
+            );
+            println!("but the permission was denied.");
+            println!();
+            println!("Press Enter to ask again, Esc to close.");
+            return;
+        }
+
+        let debug = self.config_flag("debug");
+        let layout = Layout::new(rows, self.summary_lines());
+        let item_width = cols.saturating_sub(LIST_PREFIX_WIDTH);
+        self.list_y = layout.list_y;
+
+        self.ribbon_hitboxes.clear();
+        if layout.show_ribbons {
+            self.ribbon_hitboxes = self.print_ribbons(cols);
+            println!();
+        }
+        if layout.show_spacer {
+            println!();
+        }
+
+        self.print_prompt(rows, cols);
+
+        if let Some(line) = self.shell_command().map(str::to_owned) {
+            if layout.show_list_title {
+                println!("Run command (Alt + w: floating, Alt + Enter: new tab): ");
+            }
+            self.list_entries.clear();
+            if layout.show_summary {
+                match shellwords::split(&line) {
+                    Ok(words) if !words.is_empty() => {
+                        self.print_summary("Command ->", Some(&words.join(" ")), None, cols);
+                    }
+                    Ok(_) => self.print_summary("Command ->", None, None, cols),
+                    Err(_) => println!(
+                        "{} {}",
+                        self.theme.label.paint("Command ->"),
+                        self.theme.alert.paint("mismatched quotes")
+                    ),
+                }
+                self.print_summary("Cwd ->", Some(&self.cwd.to_string_lossy()), None, cols);
+            }
+            return;
+        }
+
+        match self.current_view {
+            View::Tab => {
+                if layout.show_list_title {
+                    if !self.moving_panes.is_empty() {
+                        println!("Move pane to tab (Alt + Enter for a new tab): ");
+                    } else {
+                        println!("Tabs{}: ", self.list_title_suffix());
+                    }
+                }
+
+                let matches: Vec<(usize, &TabInfo)> = self
+                    .tab_rows()
+                    .into_iter()
+                    .map(|i| (i, &self.tab_infos[i]))
+                    .collect();
+                let (items, entries) =
+                    self.list_items(&matches, layout.list_rows, |t, selected, index| {
+                        pin_item(
+                            self.tab_list_item(
+                                t,
+                                selected,
+                                item_width,
+                                index,
+                                self.marked_tabs.contains(&t.position),
+                            ),
+                            self.is_pinned(&t.name),
+                        )
+                    });
+                self.print_list(items, layout.list_y, cols);
+                self.list_entries = entries;
+
+                if layout.show_summary {
+                    println!();
+                    match self.moving_panes.as_slice() {
+                        [] => (),
+                        [(_, title)] => {
+                            self.print_summary("Moving Pane ->", Some(title), None, cols);
+                        }
+                        panes => {
+                            let count = format!("{} panes", panes.len());
+                            self.print_summary("Moving Panes ->", Some(&count), None, cols);
+                        }
+                    }
+
+                    // pinned tabs are listed even when they do not match,
+                    // as in move_pane_to_tab
+                    if !self.moving_panes.is_empty()
+                        && !self.input.is_empty()
+                        && !self.tab_infos.iter().any(|t| self.tab_matches_query(t))
+                    {
+                        let new_tab = format!("new tab \"{}\"", self.input);
+                        self.print_summary("Selected Tab ->", Some(&new_tab), None, cols);
+                    } else {
+                        let tab = self.tab_match.and_then(|m| self.tab_infos.get(m));
+                        self.print_summary(
+                            "Selected Tab ->",
+                            tab.map(|t| t.name.as_str()),
+                            tab.map(|t| self.tab_indicators(t)),
+                            cols,
+                        );
+                    }
+                }
+            }
+            View::Pane => {
+                if layout.show_list_title {
+                    println!("Panes{}: ", self.list_title_suffix());
+                }
+
+                let panes = self.scoped_panes();
+                let pane_tab = self.selected_pane_tab();
+                let matches: Vec<(usize, &PaneInfo)> = self
+                    .pane_rows()
+                    .into_iter()
+                    .map(|i| (i, panes[i].1))
+                    .collect();
+                let labels: HashMap<u32, String> = if self.hint_mode {
+                    self.hint_labels()
+                        .into_iter()
+                        .map(|(label, _, pane)| (pane.id, label))
+                        .collect()
+                } else {
+                    HashMap::default()
+                };
+                let (items, entries) = self.list_items(
+                    &matches,
+                    layout.list_rows,
+                    |pane, selected, index| match labels.get(&pane.id) {
+                        Some(label) => self.hint_list_item(label, &pane.title, item_width),
+                        None => pin_item(
+                            self.list_item(
+                                &self.pane_text(pane),
+                                selected,
+                                item_width,
+                                index,
+                                self.marked_panes.contains(&pane.id),
+                            ),
+                            self.is_pinned(&pane.title),
+                        ),
+                    },
+                );
+                self.print_list(items, layout.list_y, cols);
+                self.list_entries = entries;
+
+                if layout.show_summary {
+                    println!();
+                    self.print_summary(
+                        "Selected Pane ->",
+                        Some(self.pane_title_match.as_str()).filter(|t| !t.is_empty()),
+                        None,
+                        cols,
+                    );
+
+                    let tab =
+                        pane_tab.and_then(|p| self.tab_infos.iter().find(|t| t.position == p));
+                    self.print_summary("Selected Tab ->", tab.map(|t| t.name.as_str()), None, cols);
+                }
+            }
+
+            View::Session => {
+                if layout.show_list_title {
+                    println!("Sessions{}: ", self.list_title_suffix());
+                }
+
+                let matches: Vec<(usize, &String)> = self
+                    .session_rows()
+                    .into_iter()
+                    .map(|i| (i, &self.sessions[i]))
+                    .collect();
+                let (items, entries) =
+                    self.list_items(&matches, layout.list_rows, |session, selected, index| {
+                        pin_item(
+                            self.list_item(
+                                &self.session_text(session),
+                                selected,
+                                item_width,
+                                index,
+                                self.marked_sessions.contains(*session),
+                            ),
+                            self.is_pinned(session),
+                        )
+                    });
+                self.print_list(items, layout.list_y, cols);
+                self.list_entries = entries;
+
+                if layout.show_summary {
+                    println!();
+                    self.print_summary(
+                        "Selected Session ->",
+                        self.session_match.as_deref(),
+                        None,
+                        cols,
+                    );
+                }
+            }
+
+            View::Command | View::Entry => {
+                if layout.show_list_title {
+                    if let View::Entry = self.current_view {
+                        println!("Entries{}: ", self.list_title_suffix());
+                    } else {
+                        println!("Commands{}: ", self.list_title_suffix());
+                    }
+                }
+
+                let matches = self.matching_commands();
+                let (items, entries) =
+                    self.list_items(&matches, layout.list_rows, |name, selected, index| {
+                        self.list_item(name, selected, item_width, index, false)
+                    });
+                self.print_list(items, layout.list_y, cols);
+                self.list_entries = entries;
+
+                if layout.show_summary {
+                    println!();
+                    let palette = self.palette();
+                    let selected = palette
+                        .iter()
+                        .find(|(i, _)| Some(*i) == self.command_match)
+                        .map(|(_, name)| *name);
+                    self.print_summary("Selected Command ->", selected, None, cols);
+                }
+            }
+
+            View::Bookmark => {
+                if layout.show_list_title {
+                    println!("Bookmarks{}: ", self.list_title_suffix());
+                }
+
+                let matches = self.matching_bookmarks();
+                let (items, entries) =
+                    self.list_items(&matches, layout.list_rows, |label, selected, index| {
+                        self.list_item(label, selected, item_width, index, false)
+                    });
+                self.print_list(items, layout.list_y, cols);
+                self.list_entries = entries;
+
+                if layout.show_summary {
+                    println!();
+                    let selected = self
+                        .bookmark_match
+                        .and_then(|i| self.bookmarks.iter().nth(i))
+                        .map(|(letter, bookmark)| self.bookmark_label(*letter, bookmark));
+                    self.print_summary("Selected Bookmark ->", selected.as_deref(), None, cols);
+                }
+            }
+
+            View::Layout => {
+                if layout.show_list_title {
+                    println!("Layouts (Alt + Enter for a new session): ");
+                }
+
+                let matches = self.matching_layouts();
+                let (items, entries) =
+                    self.list_items(&matches, layout.list_rows, |label, selected, index| {
+                        self.list_item(label, selected, item_width, index, false)
+                    });
+                self.print_list(items, layout.list_y, cols);
+                self.list_entries = entries;
+
+                if layout.show_summary {
+                    println!();
+                    let selected = self
+                        .layout_match
+                        .and_then(|i| self.layouts.get(i))
+                        .map(layout_label);
+                    self.print_summary("Selected Layout ->", selected.as_deref(), None, cols);
+                }
+            }
+
+            View::Project => {
+                if layout.show_list_title {
+                    if self.scanning_projects {
+                        println!("Projects (scanning...): ");
+                    } else if let Some(error) = &self.project_error {
+                        let error = format!(" (scan failed: {})", error);
+                        println!("Projects{}: ", self.theme.alert.paint(error));
+                    } else {
+                        println!("Projects: ");
+                    }
+                }
+
+                let matches = self.matching_projects();
+                let (items, entries) =
+                    self.list_items(&matches, layout.list_rows, |project, selected, index| {
+                        self.list_item(project, selected, item_width, index, false)
+                    });
+                self.print_list(items, layout.list_y, cols);
+                self.list_entries = entries;
+
+                if layout.show_summary {
+                    println!();
+                    self.print_summary(
+                        "Selected Project ->",
+                        self.project_match
+                            .and_then(|i| self.projects.get(i))
+                            .map(|p| project_name(p)),
+                        None,
+                        cols,
+                    );
+                }
+            }
+        }
+
+        // Key binding view
+
+        if debug {
+            let lines = [
+                format!("input: {}", self.input),
+                format!("Cursor: {}", self.input_cusror_index),
+                format!("len: {}", self.input.len()),
+                format!("tab match: {}", self.tab_match.unwrap_or(42)),
+                format!("pane match: {}", self.pane_match.unwrap_or(42)),
+                format!("focussed tab : {}", self.focus_tab_pos),
+                format!("result_index: {}", self.result_index),
+                format!(
+                    "{} {:?}",
+                    self.theme.highlight.paint("Runtime configuration:"),
+                    self.userspace_configuration
+                ),
+            ];
+
+            // only print what fits in the remaining rows
+            let remaining = rows.saturating_sub(layout.used_rows());
+            for line in lines.iter().take(remaining) {
+                println!("{}", line);
+            }
+        }
+    }
+}
+
+/// context key of the commands run on the host
+const CONTEXT_KEY: &str = "pathfinder";
+/// context value of the project scan
+const SCAN_PROJECTS: &str = "scan_projects";
+/// context value of the home directory lookup
+const HOME_DIR: &str = "home_dir";
+/// depth of the project directories under their root
+const DEFAULT_PROJECT_DEPTH: usize = 3;
+/// files or directories marking a project directory
+const DEFAULT_PROJECT_MARKERS: &str = ".git";
+
+/// layout name, flagging the built-in ones
+fn layout_label(layout: &LayoutInfo) -> String {
+    if layout.is_builtin() {
+        format!("{} (built-in)", layout.name())
+    } else {
+        layout.name().to_string()
+    }
+}
+
+/// project name from its directory
+fn project_name(path: &str) -> &str {
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(path)
+}
+
+/// kdl layout of a new tab named `name` starting in `cwd`
+fn project_tab_layout(name: &str, cwd: &str) -> String {
+    format!(
+        "layout {{ tab name={} cwd={} {{ pane }} }}",
+        kdl_string(name),
+        kdl_string(cwd)
+    )
+}
+
+/// quote a value for `sh`
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// quote a path for `sh`, keeping a leading `~` expanded
+fn shell_path(path: &str) -> String {
+    match path.strip_prefix('~') {
+        Some(rest) => format!("\"$HOME\"{}", shell_quote(rest)),
+        None => shell_quote(path),
+    }
+}
+
+/// input prefix running the rest of the query as a shell command
+const SHELL_PREFIX: char = '>';
+
+/// where a shell command pane is opened
+#[derive(Clone, Copy)]
+enum Placement {
+    Tiled,
+    Floating,
+    NewTab,
+}
+
+/// parse a shell command line, run from `cwd`
+fn command_to_run(line: &str, cwd: &Path) -> Option<CommandToRun> {
+    let words = shellwords::split(line).ok()?;
+    let (program, args) = words.split_first()?;
+    Some(CommandToRun {
+        path: PathBuf::from(program),
+        args: args.to_vec(),
+        cwd: Some(cwd.to_path_buf()),
+    })
+}
+
+/// open `command` in a new command pane, `name`
+/// is used for the tab when opened in a new tab
+fn open_command(command: CommandToRun, name: &str, placement: Placement) {
+    match placement {
+        Placement::Tiled => open_command_pane(command, BTreeMap::new()),
+        Placement::Floating => open_command_pane_floating(command, None, BTreeMap::new()),
+        Placement::NewTab => new_tabs_with_layout(&command_tab_layout(name, &command)),
+    }
+}
+
+/// config key prefix of custom entries, e.g. `entry_deploy`
+const ENTRY_PREFIX: &str = "entry_";
+
+/// custom entry declared in the configuration as
+/// `entry_<name> "<action>: <arg>; <placement>"`
+#[derive(Clone)]
+struct Entry {
+    name: String,
+    action: EntryAction,
+    placement: Placement,
+}
+
+#[derive(Clone)]
+enum EntryAction {
+    /// shell command run in a command pane
+    Run(String),
+    /// file opened in the default editor
+    Open(String),
+}
+
+impl Entry {
+    /// parse an entry, or the reason it is invalid
+    fn parse(name: &str, value: &str) -> Result<Self, String> {
+        // the placement follows the last `;`, when it is a plain word,
+        // so that a quoted command can contain `;`
+        let (value, placement) = match value.rsplit_once(';') {
+            Some((value, placement))
+                if placement.trim().chars().all(|c| c.is_ascii_alphanumeric()) =>
+            {
+                (value, placement.trim())
+            }
+            _ => (value, ""),
+        };
+        let placement = match placement {
+            "" | "tiled" => Placement::Tiled,
+            "floating" => Placement::Floating,
+            "tab" => Placement::NewTab,
+            other => return Err(format!("unknown placement \"{}\"", other)),
+        };
+
+        let Some((kind, arg)) = value.split_once(':') else {
+            return Err("expected \"run:\" or \"open:\"".to_string());
+        };
+        let arg = arg.trim().to_string();
+        if arg.is_empty() {
+            return Err(format!("nothing to {}", kind.trim()));
+        }
+        let action = match kind.trim() {
+            "run" => EntryAction::Run(arg),
+            "open" => EntryAction::Open(arg),
+            other => return Err(format!("unknown action \"{}\"", other)),
+        };
+        Ok(Entry {
+            name: name.to_string(),
+            action,
+            placement,
+        })
+    }
+}
+
+/// expand a leading `~` of path to the home directory, when known
+fn expand_home(path: &str, home: Option<&Path>) -> PathBuf {
+    match (path.strip_prefix('~'), home) {
+        (Some(""), Some(home)) => home.to_path_buf(),
+        (Some(rest), Some(home)) if rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// kdl layout of a new tab named `name` editing `file`
+fn file_tab_layout(name: &str, file: &FileToOpen) -> String {
+    let cwd = file
+        .cwd
+        .as_ref()
+        .map(|cwd| format!(" cwd={}", kdl_string(&cwd.to_string_lossy())))
+        .unwrap_or_default();
+    format!(
+        "layout {{ tab name={} {{ pane edit={}{} }} }}",
+        kdl_string(name),
+        kdl_string(&file.path.to_string_lossy()),
+        cwd
+    )
+}
+
+/// kdl layout of a new tab named `name` running `command`
+fn command_tab_layout(name: &str, command: &CommandToRun) -> String {
+    let args: Vec<String> = command.args.iter().map(|a| kdl_string(a)).collect();
+    let cwd = command
+        .cwd
+        .as_ref()
+        .map(|cwd| format!(" cwd={}", kdl_string(&cwd.to_string_lossy())))
+        .unwrap_or_default();
+    format!(
+        "layout {{ tab name={} {{ pane command={}{} {{ args {} }} }} }}",
+        kdl_string(name),
+        kdl_string(&command.path.to_string_lossy()),
+        cwd,
+        args.join(" ")
+    )
+}
+
+/// quote a kdl string value
+fn kdl_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// zellij action listed in the command palette
+struct Command {
+    name: &'static str,
+    run: fn(),
+    /// acts on the focused pane, which is the
+    /// sidebar itself in sidebar mode
+    focused_pane: bool,
+}
+
+/// actions available from the plugin API
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "New pane",
+        focused_pane: false,
+        run: || open_terminal("."),
+    },
+    Command {
+        name: "New floating pane",
+        focused_pane: false,
+        run: || open_terminal_floating(".", None),
+    },
+    Command {
+        name: "New tab",
+        focused_pane: false,
+        run: new_tab,
+    },
+    Command {
+        name: "Close pane",
+        focused_pane: true,
+        run: close_focus,
+    },
+    Command {
+        name: "Close tab",
+        focused_pane: false,
+        run: close_focused_tab,
+    },
+    Command {
+        name: "Next tab",
+        focused_pane: false,
+        run: go_to_next_tab,
+    },
+    Command {
+        name: "Previous tab",
+        focused_pane: false,
+        run: go_to_previous_tab,
+    },
+    Command {
+        name: "Focus next pane",
+        focused_pane: false,
+        run: focus_next_pane,
+    },
+    Command {
+        name: "Focus previous pane",
+        focused_pane: false,
+        run: focus_previous_pane,
+    },
+    Command {
+        name: "Toggle fullscreen",
+        focused_pane: true,
+        run: toggle_focus_fullscreen,
+    },
+    Command {
+        name: "Toggle pane embed or floating",
+        focused_pane: true,
+        run: toggle_pane_embed_or_eject,
+    },
+    Command {
+        name: "Toggle pane frames",
+        focused_pane: false,
+        run: toggle_pane_frames,
+    },
+    Command {
+        name: "Toggle sync panes",
+        focused_pane: false,
+        run: toggle_active_tab_sync,
+    },
+    Command {
+        name: "Next swap layout",
+        focused_pane: false,
+        run: next_swap_layout,
+    },
+    Command {
+        name: "Previous swap layout",
+        focused_pane: false,
+        run: previous_swap_layout,
+    },
+    Command {
+        name: "Clear scrollback",
+        focused_pane: true,
+        run: clear_screen,
+    },
+    Command {
+        name: "Edit scrollback",
+        focused_pane: true,
+        run: edit_scrollback,
+    },
+    Command {
+        name: "Scroll to top",
+        focused_pane: true,
+        run: scroll_to_top,
+    },
+    Command {
+        name: "Scroll to bottom",
+        focused_pane: true,
+        run: scroll_to_bottom,
+    },
+    Command {
+        name: "Detach",
+        focused_pane: false,
+        run: detach,
+    },
+];
+
+/// seconds during which a rename can be undone
+const UNDO_RENAME_SECONDS: f64 = 10.0;
+
+/// destructive action waiting for confirmation
+enum PendingAction {
+    /// tab positions
+    CloseTabs(Vec<usize>),
+    ClosePanes(Vec<u32>),
+    KillSessions(Vec<String>),
+}
+
+impl PendingAction {
+    fn is_empty(&self) -> bool {
+        match self {
+            PendingAction::CloseTabs(t) => t.is_empty(),
+            PendingAction::ClosePanes(p) => p.is_empty(),
+            PendingAction::KillSessions(s) => s.is_empty(),
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            PendingAction::CloseTabs(t) => format!(" Close {} tab(s)?", t.len()),
+            PendingAction::ClosePanes(p) => format!(" Close {} pane(s)?", p.len()),
+            PendingAction::KillSessions(s) => format!(" Kill {} session(s)?", s.len()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum RenameTarget {
+    /// tab position
+    Tab(usize),
+    /// terminal pane id
+    Pane(u32),
+}
+
+fn rename_target(target: RenameTarget, name: &str) {
+    match target {
+        RenameTarget::Tab(position) => rename_tab(position as u32 + 1, name),
+        RenameTarget::Pane(id) => rename_terminal_pane(id, name),
+    }
+}
+
+/// modal prompt displayed over the current view
+enum Overlay {
+    /// y/n confirmation of a destructive action
+    Confirm(PendingAction),
+    /// new name being typed
+    Rename(RenameTarget, String),
+    /// aliases and tags being typed
+    Annotate(Annotated, String),
+    /// letter to bookmark the selected entry with
+    Bookmark,
+    /// letter of the bookmark to jump to
+    Jump,
+}
+
+/// bookmarked tab or pane
+#[derive(Clone)]
+enum Bookmark {
+    /// tab, found by name
+    Tab(String),
+    /// pane, found by id or title
+    Pane { id: u32, tab: String, title: String },
+}
+
+/// focus the pane, or the tab when there is no pane
+fn focus_target(target: (usize, Option<u32>)) {
+    match target {
+        (_, Some(pane_id)) => focus_terminal_pane(pane_id, true),
+        (position, None) => switch_tab_to(position as u32 + 1),
+    }
+}
+
+/// query prefix of a tag, searching the panes of all tabs
+const TAG_PREFIX: char = '#';
+
+/// state of the permission requests
+#[derive(Clone, Copy, PartialEq)]
+enum Permissions {
+    RequestingRead,
+    RequestingActions,
+    /// reading again after the actions were denied
+    RequestingReadOnly,
+    /// reading the session is denied, nothing to show
+    Denied,
+    /// only reading the session is granted
+    ReadOnly,
+    Granted,
+}
+
+/// keys moving in the lists and editing the query,
+/// available without the action permissions
+fn is_browsing_key(key: &KeyWithModifier) -> bool {
+    match key.bare_key {
+        BareKey::Up
+        | BareKey::Down
+        | BareKey::PageUp
+        | BareKey::Left
+        | BareKey::Right
+        | BareKey::Backspace
+        | BareKey::Tab => true,
+        BareKey::Char(_) => key.has_no_modifiers(),
+        _ => false,
+    }
+}
+
+/// query and selection of a view, restored when switching back to it
+#[derive(Clone)]
+struct ViewState {
+    input: String,
+    cursor: usize,
+    selected: Option<Selected>,
+}
+
+/// identity of the selected entry of a view, which
+/// may move in the list while another view is shown
+#[derive(Clone)]
+enum Selected {
+    Tab(String),
+    Pane(u32),
+    Session(String),
+    /// command palette item, indexes do not change
+    Command(usize),
+    Project(String),
+    Layout(LayoutInfo),
+    Bookmark(char),
+}
+
+/// tab, pane or session with aliases and tags
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Annotated {
+    /// tab, by name
+    Tab(String),
+    /// terminal pane, by id
+    Pane(u32),
+    /// session, by name
+    Session(String),
+}
+
+/// queries remembered per view by default
+const DEFAULT_HISTORY_LENGTH: usize = 50;
+
+/// plugin data directory
+const DATA_DIR: &str = "/data";
+
+/// escape the tabs, newlines and backslashes of a field
+/// saved in a tab separated data file
+fn escape_field(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// reverse of `escape_field`
+fn unescape_field(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// indexes of the (index, pinned, matching) rows to list:
+/// the pinned ones first, whether they match or not
+fn pinned_first(rows: impl Iterator<Item = (usize, bool, bool)>) -> Vec<usize> {
+    let (pinned, others): (Vec<_>, Vec<_>) = rows
+        .filter(|(_, pinned, matching)| *pinned || *matching)
+        .partition(|(_, pinned, _)| *pinned);
+    pinned
+        .into_iter()
+        .chain(others)
+        .map(|(i, _, _)| i)
+        .collect()
+}
+
+/// row after (or before) `current`, wrapping around
+fn step_row(rows: &[usize], current: usize, forward: bool) -> Option<usize> {
+    let len = rows.len();
+    let next = match rows.iter().position(|i| *i == current) {
+        Some(pos) if forward => (pos + 1) % len,
+        Some(pos) => (pos + len - 1) % len,
+        None if forward => 0,
+        None => len.checked_sub(1)?,
+    };
+    rows.get(next).copied()
+}
+
+/// highlight a pinned list item
+fn pin_item(item: NestedListItem, pinned: bool) -> NestedListItem {
+    if pinned {
+        item.color_range(2, ..)
+    } else {
+        item
+    }
+}
+
+/// match a name against a pattern where `*` matches any characters
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // no `*`, exact match
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// insert the value in the set, or remove it if already present
+fn toggle<T: Ord>(set: &mut BTreeSet<T>, value: T) {
+    if !set.remove(&value) {
+        set.insert(value);
+    }
+}
+
+/// quick select index displayed in front of a list row,
+/// digits 1 to 9 then 0 for the tenth row
+fn row_label(row: usize) -> String {
+    match row {
+        0..=8 => (row + 1).to_string(),
+        9 => "0".to_string(),
+        _ => " ".to_string(),
+    }
+}
+
+/// list row selected by a quick select digit
+fn digit_row(digit: char) -> Option<usize> {
+    match digit.to_digit(10)? {
+        0 => Some(9),
+        d => Some(d as usize - 1),
+    }
+}
+
+/// default keys used for pane hint labels
+const DEFAULT_HINT_KEYS: &str = "asdfghjklqwertyuiopzxcvbnm";
+
+/// build count hint labels from at least two distinct keys.
+/// Labels all have the same length, the shortest one giving
+/// enough labels, so that no label is a prefix of another
+fn hint_labels(count: usize, keys: &[char]) -> Vec<String> {
+    let base = keys.len();
+    if base < 2 {
+        return keys.iter().take(count).map(|k| k.to_string()).collect();
+    }
+
+    let mut length = 1;
+    let mut capacity = base;
+    while capacity < count {
+        length += 1;
+        capacity = capacity.saturating_mul(base);
+    }
+
+    (0..count)
+        .map(|mut n| {
+            let mut label = vec![keys[0]; length];
+            for slot in label.iter_mut().rev() {
+                *slot = keys[n % base];
+                n /= base;
+            }
+            label.into_iter().collect()
+        })
+        .collect()
+}
+
+/// name of the pipe messages handled by the plugin
+const PIPE_NAME: &str = "pathfinder";
+
+/// width taken by the nested list bullet
+const LIST_PREFIX_WIDTH: usize = 3;
+
+/// extra width of a ribbon around its label
+const RIBBON_PADDING: usize = 4;
+
+/// minimum number of rows kept for the result list
+/// before optional panels are displayed
+const MIN_LIST_ROWS: usize = 3;
+
+/// rows allocated to each part of the UI,
+/// optional panels are hidden when the pane is too small
+struct Layout {
+    show_ribbons: bool,
+    show_spacer: bool,
+    show_list_title: bool,
+    show_summary: bool,
+    summary_lines: usize,
+    /// line at which the result list is printed
+    list_y: usize,
+    /// max number of list rows
+    list_rows: usize,
+}
+
+impl Layout {
+    fn new(rows: usize, summary_lines: usize) -> Self {
+        // the prompt is always displayed
+        let mut remaining = rows.saturating_sub(1);
+
+        // optional panels by order of priority
+        let mut take = |cost: usize| {
+            if remaining >= cost + MIN_LIST_ROWS {
+                remaining -= cost;
+                true
+            } else {
+                false
+            }
+        };
+        let show_ribbons = take(1);
+        // blank line and summary lines
+        let show_summary = take(summary_lines + 1);
+        let show_list_title = take(1);
+        let show_spacer = take(1);
+
+        let list_y = show_ribbons as usize + show_spacer as usize + 1 + show_list_title as usize;
+
+        Self {
+            show_ribbons,
+            show_spacer,
+            show_list_title,
+            show_summary,
+            summary_lines,
+            list_y,
+            list_rows: remaining,
+        }
+    }
+
+    /// number of rows used when the list is full
+    fn used_rows(&self) -> usize {
+        let summary = if self.show_summary {
+            self.summary_lines + 1
+        } else {
+            0
+        };
+        self.list_y + self.list_rows + summary
+    }
+}
+
+/// range of entries to display so that the selected one is visible,
+/// and whether an overflow indicator should be added
+fn list_window(len: usize, selected: usize, capacity: usize) -> (std::ops::Range<usize>, bool) {
+    if len <= capacity {
+        return (0..len, false);
+    }
+
+    // the end of the list is visible
+    if selected + capacity >= len {
+        return (len - capacity..len, false);
+    }
+
+    // keep one row for the overflow indicator
+    let (visible, overflow) = if capacity > 1 {
+        (capacity - 1, true)
+    } else {
+        (capacity, false)
+    };
+
+    let start = if selected >= visible {
+        selected + 1 - visible
+    } else {
+        0
+    };
+    (start..start + visible, overflow)
+}
+
+/// truncate text to a display width, adding an ellipsis
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let mut truncated = String::new();
+    let mut current = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if current + w + 1 > width {
+            break;
+        }
+        truncated.push(c);
+        current += w;
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// keep the match indices of the characters of text
+/// still displayed once truncated
+fn visible_indices(indices: Vec<usize>, text: &str, truncated: &str) -> Vec<usize> {
+    let visible = if truncated == text {
+        text.chars().count()
+    } else {
+        // without the ellipsis
+        truncated.chars().count().saturating_sub(1)
+    };
+    indices.into_iter().filter(|i| *i < visible).collect()
+}
+
+/// truncate the beginning of text to a display width,
+/// adding an ellipsis
+fn truncate_start(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let mut kept = vec![];
+    let mut current = 0;
+    for c in text.chars().rev() {
+        let w = c.width().unwrap_or(0);
+        if current + w + 1 > width {
+            break;
+        }
+        kept.push(c);
+        current += w;
+    }
+    std::iter::once('…').chain(kept.into_iter().rev()).collect()
+}
+
+/// display width of a string containing ANSI escape codes
+fn ansi_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
+    for c in text.chars() {
+        if in_escape {
+            if c == 'm' {
+                in_escape = false;
+            }
+        } else if c == '\x1b' {
+            in_escape = true;
+        } else {
+            width += c.width().unwrap_or(0);
+        }
+    }
+    width
+}
+
+/// kind of tab badge, used to pick its color
+enum Badge {
+    Muted,
+    Info,
+    Alert,
+    Highlight,
+}
+
+impl Badge {
+    /// color index level of zellij UI components
+    fn color_index(&self) -> Option<usize> {
+        match self {
+            Badge::Muted => None,
+            Badge::Alert => Some(1),
+            Badge::Info => Some(2),
+            Badge::Highlight => Some(3),
+        }
+    }
+}
+
+/// Colors used to render each UI element.
+///
+/// Derived from the zellij palette received on `ModeUpdate`,
+/// each element can be overridden in the plugin configuration
+/// with `color_<element> "<color>"` where color is a palette
+/// color name (e.g. `blue`), a `#rrggbb` hex or a 256 color index.
+struct Theme {
+    prompt: Style,
+    cursor: Style,
+    input: Style,
+    placeholder: Style,
+    selected: Style,
+    muted: Style,
+    label: Style,
+    badge: Style,
+    alert: Style,
+    highlight: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new(
+            &zellij_utils::shared::default_palette(),
+            &BTreeMap::default(),
+        )
+    }
+}
+
+impl Theme {
+    fn new(palette: &Palette, configuration: &BTreeMap<String, String>) -> Self {
+        let mode = configuration.get("color_mode").map(|m| m.as_str());
+        let mut theme = match mode {
+            Some("no_color") => return Self::no_color(),
+            Some("high_contrast") => Self::high_contrast(palette),
+            _ => Self::from_palette(palette),
+        };
+
+        // per element overrides
+        for (element, style) in [
+            ("prompt", &mut theme.prompt),
+            ("cursor", &mut theme.cursor),
+            ("input", &mut theme.input),
+            ("placeholder", &mut theme.placeholder),
+            ("selected", &mut theme.selected),
+            ("muted", &mut theme.muted),
+            ("label", &mut theme.label),
+            ("badge", &mut theme.badge),
+            ("alert", &mut theme.alert),
+            ("highlight", &mut theme.highlight),
+        ] {
+            if let Some(color) = configuration
+                .get(&format!("color_{}", element))
+                .and_then(|c| parse_color(c, palette))
+            {
+                style.foreground = Some(color);
+            }
+        }
+
+        theme
+    }
+
+    fn from_palette(palette: &Palette) -> Self {
+        let fg = to_colour(palette.fg);
+        Self {
+            prompt: Style::new().fg(to_colour(palette.cyan)).bold(),
+            cursor: Style::new().fg(fg).bold(),
+            input: Style::new().fg(fg),
+            placeholder: Style::new().fg(fg).italic(),
+            selected: Style::new().fg(to_colour(palette.blue)).bold(),
+            muted: Style::new().fg(fg).italic(),
+            label: Style::new().fg(fg).bold(),
+            badge: Style::new().fg(to_colour(palette.cyan)),
+            alert: Style::new().fg(to_colour(palette.red)).bold(),
+            highlight: Style::new().fg(to_colour(palette.green)).bold(),
+        }
+    }
+
+    /// only use the foreground/background of the theme,
+    /// selection is shown in reverse video
+    fn high_contrast(palette: &Palette) -> Self {
+        let fg = Style::new().fg(to_colour(palette.fg));
+        Self {
+            prompt: fg.bold(),
+            cursor: fg.bold(),
+            input: fg.bold(),
+            placeholder: fg.italic(),
+            selected: fg.bold().reverse(),
+            muted: fg.italic(),
+            label: fg.bold().underline(),
+            badge: fg,
+            alert: fg.bold().reverse(),
+            highlight: fg.bold().underline(),
+        }
+    }
+
+    /// no color at all, only text attributes
+    fn no_color() -> Self {
+        let plain = Style::new();
+        Self {
+            prompt: plain.bold(),
+            cursor: plain.bold(),
+            input: plain,
+            placeholder: plain.italic(),
+            selected: plain.bold().reverse(),
+            muted: plain.italic(),
+            label: plain.bold(),
+            badge: plain,
+            alert: plain.bold(),
+            highlight: plain.underline(),
+        }
+    }
+}
+
+fn to_colour(color: PaletteColor) -> Colour {
+    match color {
+        PaletteColor::Rgb((r, g, b)) => Colour::RGB(r, g, b),
+        PaletteColor::EightBit(c) => Colour::Fixed(c),
+    }
+}
+
+/// parse a color from configuration: palette color name,
+/// `#rrggbb` hex or 256 color index
+fn parse_color(value: &str, palette: &Palette) -> Option<Colour> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+        return Some(Colour::RGB(r, g, b));
+    }
+
+    if let Ok(index) = value.parse::<u8>() {
+        return Some(Colour::Fixed(index));
+    }
+
+    let color = match value {
+        "fg" => palette.fg,
+        "bg" => palette.bg,
+        "black" => palette.black,
+        "red" => palette.red,
+        "green" => palette.green,
+        "yellow" => palette.yellow,
+        "blue" => palette.blue,
+        "magenta" => palette.magenta,
+        "cyan" => palette.cyan,
+        "white" => palette.white,
+        "orange" => palette.orange,
+        "gray" => palette.gray,
+        "purple" => palette.purple,
+        "gold" => palette.gold,
+        "silver" => palette.silver,
+        "pink" => palette.pink,
+        "brown" => palette.brown,
+        _ => return None,
+    };
+    Some(to_colour(color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // zellij host functions are only linked when running in zellij,
+    // the tested helpers never call them
+    #[cfg(not(target_arch = "wasm32"))]
+    #[no_mangle]
+    extern "C" fn host_run_plugin_command() {}
+
+    #[test]
+    fn parse_color_rejects_non_ascii_hex() {
+        let palette = zellij_utils::shared::default_palette();
+        assert!(parse_color("#aéb12", &palette).is_none());
+        assert!(parse_color("#12345", &palette).is_none());
+        assert!(parse_color("#ff8800", &palette).is_some());
+    }
+
+    #[test]
+    fn entry_parse_placement_after_the_last_semicolon() {
+        let entry = Entry::parse("deploy", "run: make deploy; floating").unwrap();
+        assert!(matches!(&entry.action, EntryAction::Run(c) if c == "make deploy"));
+        assert!(matches!(entry.placement, Placement::Floating));
+
+        let entry = Entry::parse("notes", "open: ~/notes.md").unwrap();
+        assert!(matches!(&entry.action, EntryAction::Open(p) if p == "~/notes.md"));
+        assert!(matches!(entry.placement, Placement::Tiled));
+    }
+
+    #[test]
+    fn entry_parse_keeps_quoted_semicolons() {
+        let entry = Entry::parse("check", "run: sh -c 'fmt; test'").unwrap();
+        assert!(matches!(&entry.action, EntryAction::Run(c) if c == "sh -c 'fmt; test'"));
+        let entry = Entry::parse("check", "run: sh -c 'fmt; test'; tab").unwrap();
+        assert!(matches!(&entry.action, EntryAction::Run(c) if c == "sh -c 'fmt; test'"));
+        assert!(matches!(entry.placement, Placement::NewTab));
+    }
+
+    #[test]
+    fn entry_parse_reports_invalid_entries() {
+        assert!(Entry::parse("a", "run: htop; flaoting").is_err());
+        assert!(Entry::parse("a", "exec: htop").is_err());
+        assert!(Entry::parse("a", "htop").is_err());
+        assert!(Entry::parse("a", "run: ").is_err());
+    }
+
+    #[test]
+    fn expand_home_only_expands_a_leading_tilde() {
+        let home = Path::new("/home/me");
+        assert_eq!(expand_home("~/notes.md", Some(home)), home.join("notes.md"));
+        assert_eq!(expand_home("~", Some(home)), home);
+        assert_eq!(expand_home("~other/x", Some(home)), Path::new("~other/x"));
+        assert_eq!(expand_home("a/~/b", Some(home)), Path::new("a/~/b"));
+        assert_eq!(expand_home("~/notes.md", None), Path::new("~/notes.md"));
+    }
+
+    #[test]
+    fn escaped_fields_round_trip() {
+        for field in [
+            "plain",
+            "tab\there",
+            "two\nlines",
+            "back\\slash\\t",
+            "",
+            "\\",
+        ] {
+            let escaped = escape_field(field);
+            assert!(!escaped.contains(['\t', '\n']));
+            assert_eq!(unescape_field(&escaped), field);
+        }
+    }
+
+    #[test]
+    fn glob_match_without_star_is_exact() {
+        assert!(glob_match("main", "main"));
+        assert!(!glob_match("main", "main2"));
+        assert!(!glob_match("main", "mai"));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "main"));
+    }
+
+    #[test]
+    fn glob_match_stars_at_the_ends() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("dev*", "dev"));
+        assert!(glob_match("dev*", "dev-api"));
+        assert!(!glob_match("dev*", "prod-dev"));
+        assert!(glob_match("*log", "syslog"));
+        assert!(!glob_match("*log", "logs"));
+        assert!(glob_match("*api*", "dev-api-1"));
+    }
+
+    #[test]
+    fn glob_match_does_not_overlap_parts() {
+        assert!(!glob_match("ab*b", "ab"));
+        assert!(glob_match("ab*b", "abb"));
+        assert!(glob_match("a*b*c", "a-b-c"));
+        assert!(!glob_match("a*b*c", "a-c-b"));
+    }
+
+    #[test]
+    fn pinned_first_keeps_pinned_rows_even_unmatched() {
+        let rows = [
+            (0, false, true),
+            (1, true, false),
+            (2, false, false),
+            (3, true, true),
+        ];
+        assert_eq!(pinned_first(rows.into_iter()), vec![1, 3, 0]);
+        assert!(pinned_first(std::iter::empty()).is_empty());
+    }
+
+    #[test]
+    fn pinned_first_keeps_duplicates_in_order() {
+        let rows = [
+            (0, false, true),
+            (1, false, true),
+            (2, true, true),
+            (3, true, true),
+        ];
+        assert_eq!(pinned_first(rows.into_iter()), vec![2, 3, 0, 1]);
+    }
+
+    #[test]
+    fn step_row_wraps_around() {
+        let rows = [4, 1, 7];
+        assert_eq!(step_row(&rows, 4, true), Some(1));
+        assert_eq!(step_row(&rows, 7, true), Some(4));
+        assert_eq!(step_row(&rows, 4, false), Some(7));
+        assert_eq!(step_row(&rows, 1, false), Some(4));
+    }
+
+    #[test]
+    fn step_row_from_an_unlisted_row() {
+        let rows = [4, 1, 7];
+        assert_eq!(step_row(&rows, 9, true), Some(4));
+        assert_eq!(step_row(&rows, 9, false), Some(7));
+        assert_eq!(step_row(&[], 0, true), None);
+        assert_eq!(step_row(&[], 0, false), None);
+        assert_eq!(step_row(&[3], 3, true), Some(3));
+    }
+
+    #[test]
+    fn pin_item_colors_the_whole_pinned_row() {
+        let item = || NestedListItem::new("ab");
+        assert_eq!(pin_item(item(), false).serialize(), item().serialize());
+        assert_eq!(pin_item(item(), true).serialize(), "$$0,1$97,98");
+    }
+
+    fn tabs(names: &[&str]) -> Vec<TabInfo> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(position, name)| TabInfo {
+                position,
+                name: name.to_string(),
+                active: position == 0,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// state with `previous` tabs, `selected` selected and
+    /// `marked` marked, updated to `current` tabs
+    fn resolve(previous: &[&str], current: &[&str], selected: usize, marked: &[usize]) -> State {
+        let mut state = State {
+            tab_infos: tabs(current),
+            tab_match: Some(selected),
+            marked_tabs: marked.iter().copied().collect(),
+            ..Default::default()
+        };
+        state.resolve_tabs(&tabs(previous));
+        state
+    }
+
+    #[test]
+    fn resolve_tabs_follows_a_moved_tab() {
+        let state = resolve(&["a", "b", "c"], &["b", "c", "a"], 0, &[1]);
+        assert_eq!(state.tab_match, Some(2));
+        assert_eq!(state.result_index, 2);
+        assert_eq!(state.marked_tabs, BTreeSet::from([0]));
+    }
+
+    #[test]
+    fn resolve_tabs_follows_a_renamed_tab() {
+        let state = resolve(&["a", "b"], &["a", "renamed"], 1, &[]);
+        assert_eq!(state.tab_match, Some(1));
+    }
+
+    #[test]
+    fn resolve_tabs_reports_a_closed_tab() {
+        let state = resolve(&["a", "b", "c"], &["a", "c"], 1, &[1, 2]);
+        assert_eq!(state.tab_match, None);
+        assert_eq!(state.selection_lost.as_deref(), Some("b"));
+        assert_eq!(state.marked_tabs, BTreeSet::from([1]));
+    }
+
+    #[test]
+    fn resolve_tabs_picks_the_closest_duplicate() {
+        let state = resolve(&["x", "a", "x"], &["a", "x", "x"], 2, &[]);
+        assert_eq!(state.tab_match, Some(2));
+    }
+
+    #[test]
+    fn resolve_tabs_without_tabs() {
+        let state = resolve(&[], &["a"], 0, &[]);
+        assert_eq!(state.tab_match, Some(0));
+        let state = resolve(&["a"], &[], 0, &[]);
+        assert_eq!(state.tab_match, None);
+    }
+
+    fn keys(keys: &str) -> Vec<char> {
+        keys.chars().collect()
+    }
+
+    #[test]
+    fn hint_labels_use_single_keys_when_enough() {
+        assert_eq!(hint_labels(3, &keys("asd")), vec!["a", "s", "d"]);
+        assert!(hint_labels(0, &keys("asd")).is_empty());
+    }
+
+    #[test]
+    fn hint_labels_have_the_same_length() {
+        let labels = hint_labels(5, &keys("as"));
+        assert_eq!(labels, vec!["aaa", "aas", "asa", "ass", "saa"]);
+    }
+
+    #[test]
+    fn hint_labels_extend_past_squared_keys() {
+        let labels = hint_labels(10, &keys("asd"));
+        assert_eq!(labels.len(), 10);
+        assert!(labels.iter().all(|l| l.chars().count() == 3));
+        let unique: BTreeSet<&String> = labels.iter().collect();
+        assert_eq!(unique.len(), 10);
+    }
 }